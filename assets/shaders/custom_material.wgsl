@@ -1,11 +1,111 @@
-#import bevy_pbr::forward_io::VertexOutput
+#import bevy_pbr::{
+    mesh_functions::get_world_from_local,
+    mesh_view_bindings::{globals, view},
+    view_transformations::position_world_to_clip,
+}
+
+struct PointMaterial {
+    near_color: vec4<f32>,
+    far_color: vec4<f32>,
+    source_colors: array<vec4<f32>, 8>,
+    color_distance_factor: f32,
+    fade_duration: f32,
+    min_intensity: f32,
+    point_radius: f32,
+    point_pixels: f32,
+    point_attenuation: f32,
+};
+
+@group(2) @binding(0) var<uniform> material: PointMaterial;
+
+// `globals.time` wraps around after this many seconds, the default `Time::wrap_period`
+const TIME_WRAP_PERIOD: f32 = 3600.0;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+    // distance, intensity, timestamp, source
+    @location(1) point_data: vec4<f32>,
+    // offset from the point, in units of the point radius
+    @location(2) offset: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) point_data: vec4<f32>,
+    @location(1) offset: vec2<f32>,
+};
+
+// world space size of a pixel at the given distance along the view direction
+fn pixel_size(view_depth: f32) -> f32 {
+    return 2.0 * view_depth / (view.viewport.w * view.clip_from_view[1][1]);
+}
 
-@group(2) @binding(0) var<uniform> material_color: vec4<f32>;
-@group(2) @binding(1) var<uniform> hue: f32;
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    let center = (get_world_from_local(vertex.instance_index) * vec4<f32>(vertex.position, 1.0)).xyz;
+    let view_depth = -(view.view_from_world * vec4<f32>(center, 1.0)).z;
+
+    var radius = material.point_radius;
+#ifdef POINT_SPLAT
+    radius = material.point_pixels * pixel_size(view_depth);
+#endif
+#ifdef POINT_ATTENUATED
+    let distance = length(view.world_position - center);
+    radius = material.point_pixels / (1.0 + material.point_attenuation * distance) * pixel_size(view_depth);
+#endif
+
+#ifdef POINT_BILLBOARD
+    let right = view.world_from_view[0].xyz;
+    let up = view.world_from_view[1].xyz;
+    let world_position = center + (right * vertex.offset.x + up * vertex.offset.y) * radius;
+#else
+    let world_position = center + vertex.offset * radius;
+#endif
+
+    out.clip_position = position_world_to_clip(world_position);
+    out.point_data = vertex.point_data;
+    out.offset = vertex.offset.xy;
+    return out;
+}
 
 @fragment
 fn fragment(
-    mesh: VertexOutput,
+    in: VertexOutput,
 ) -> @location(0) vec4<f32> {
-    return material_color * hue;
+    let distance = in.point_data.x;
+    let intensity = mix(material.min_intensity, 1.0, in.point_data.y);
+    let timestamp = in.point_data.z;
+    let source = min(u32(in.point_data.w), 7u);
+
+    let gradient = mix(
+        material.near_color,
+        material.far_color,
+        saturate(distance * material.color_distance_factor),
+    );
+    let color = gradient * material.source_colors[source];
+
+    var shape = 1.0;
+#ifdef POINT_ROUND
+    let radius_squared = dot(in.offset, in.offset);
+    if radius_squared > 1.0 {
+        discard;
+    }
+#ifdef POINT_SPLAT
+    shape = exp(-4.0 * radius_squared);
+#endif
+#endif
+
+    var fade = 1.0;
+    if material.fade_duration > 0.0 {
+        // timestamps are `Time<Virtual>` elapsed seconds, which don't wrap
+        var age = globals.time - timestamp % TIME_WRAP_PERIOD;
+        if age < 0.0 {
+            age += TIME_WRAP_PERIOD;
+        }
+        fade = saturate(1.0 - age / material.fade_duration);
+    }
+
+    return color * intensity * shape * fade;
 }
@@ -0,0 +1,135 @@
+//! `PointStorage` backends at different point counts
+
+use bevy::prelude::*;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use lidar::{
+    octree::{OctreeStorage, DEFAULT_LEAF_SIZE},
+    space::{LidarPoint, PointStorage, VecStorage},
+};
+
+const SIZES: &[usize] = &[10_000, 100_000, 1_000_000];
+/// points are spread through a cube this many meters across, centered on the origin
+const EXTENT: f32 = 200.0;
+/// side length of the boxes passed to `query_aabb`
+const QUERY_SIZE: f32 = 16.0;
+
+fn random_points(count: usize) -> (Vec<LidarPoint>, Vec<Entity>) {
+    let mut rng = StdRng::seed_from_u64(count as u64);
+    let points = (0..count)
+        .map(|i| {
+            let position = Vec3::new(rng.random(), rng.random(), rng.random()) * EXTENT
+                - Vec3::splat(EXTENT / 2.0);
+            LidarPoint {
+                position,
+                normal: Vec3::Y,
+                distance: position.length(),
+                incidence_angle: 0.0,
+                intensity: 1.0,
+                timestamp: i as f32 * 0.001,
+                source: 0,
+                returns: 1,
+            }
+        })
+        .collect();
+    let entities = (0..count as u32).map(Entity::from_raw).collect();
+    (points, entities)
+}
+
+fn query_boxes() -> Vec<(Vec3, Vec3)> {
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..16)
+        .map(|_| {
+            let min = Vec3::new(rng.random(), rng.random(), rng.random()) * (EXTENT - QUERY_SIZE)
+                - Vec3::splat(EXTENT / 2.0);
+            (min, min + Vec3::splat(QUERY_SIZE))
+        })
+        .collect()
+}
+
+/// `--storage` names of every backend
+const BACKENDS: &[&str] = &["vec", "octree"];
+
+fn backend(name: &str, limit: usize) -> Box<dyn PointStorage> {
+    match name {
+        "vec" => Box::new(VecStorage::new(limit)),
+        "octree" => Box::new(OctreeStorage::new(DEFAULT_LEAF_SIZE, limit)),
+        _ => unreachable!("unknown backend {name}"),
+    }
+}
+
+fn add_points(c: &mut Criterion) {
+    let mut group = c.benchmark_group("add_points");
+    group.sample_size(10);
+    for &size in SIZES {
+        let (points, entities) = random_points(size);
+        group.throughput(Throughput::Elements(size as u64));
+        for &name in BACKENDS {
+            group.bench_function(BenchmarkId::new(name, size), |b| {
+                b.iter_batched(
+                    || backend(name, size),
+                    |mut storage| {
+                        storage.add_points(&points, &entities);
+                        storage
+                    },
+                    BatchSize::PerIteration,
+                );
+            });
+        }
+    }
+    group.finish();
+}
+
+/// trims a full storage down to half its points
+fn trim(c: &mut Criterion) {
+    let mut group = c.benchmark_group("trim");
+    group.sample_size(10);
+    for &size in SIZES {
+        let (points, entities) = random_points(size);
+        group.throughput(Throughput::Elements(size as u64 / 2));
+        for &name in BACKENDS {
+            group.bench_function(BenchmarkId::new(name, size), |b| {
+                b.iter_batched(
+                    || {
+                        let mut storage = backend(name, size);
+                        storage.add_points(&points, &entities);
+                        storage.set_limit(size / 2);
+                        storage
+                    },
+                    |mut storage| {
+                        let trimmed = storage.trim();
+                        (storage, trimmed)
+                    },
+                    BatchSize::PerIteration,
+                );
+            });
+        }
+    }
+    group.finish();
+}
+
+fn query_aabb(c: &mut Criterion) {
+    let mut group = c.benchmark_group("query_aabb");
+    let boxes = query_boxes();
+    for &size in SIZES {
+        let (points, entities) = random_points(size);
+        group.throughput(Throughput::Elements(boxes.len() as u64));
+        for &name in BACKENDS {
+            let mut storage = backend(name, size);
+            storage.add_points(&points, &entities);
+            group.bench_function(BenchmarkId::new(name, size), |b| {
+                b.iter(|| {
+                    boxes
+                        .iter()
+                        .map(|&(min, max)| storage.query_aabb(min, max).len())
+                        .sum::<usize>()
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, add_points, trim, query_aabb);
+criterion_main!(benches);
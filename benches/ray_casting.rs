@@ -0,0 +1,183 @@
+//! throughput of `lidar_new_points`, firing shots at a fixed scene without rendering,
+//! and the `SceneBvh` it casts against compared to the `MeshRayCast` it replaced
+
+use bevy::{
+    diagnostic::DiagnosticsStore, ecs::system::SystemState, prelude::*, render::primitives::Aabb,
+    time::TimeUpdateStrategy,
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::{rngs::StdRng, SeedableRng};
+
+use lidar::{
+    bvh::{BvhPlugin, SceneBvh},
+    gun::{LidarShotFired, LidarShotResolved},
+    noise::LidarNoise,
+    save::UnsavedScan,
+    scanner::ScanPattern,
+    settings::GameSettings,
+    space::{
+        lidar_new_points, LidarInteractable, LidarReflectivity, MissLog, PendingShots, ShotBudget,
+        Space, VecStorage,
+    },
+    util::LidarRng,
+};
+
+const SHOTS_PER_FRAME: &[usize] = &[100, 1_000, 10_000];
+const POINT_LIMIT: usize = 100_000;
+const SPREAD: f32 = 0.4;
+const MAX_RANGE: f32 = 100.0;
+
+/// spawns a mesh as the render world would see it, since nothing computes bounds or visibility here
+fn spawn_mesh(world: &mut World, mesh: Mesh, transform: Transform) -> Entity {
+    let aabb = mesh.compute_aabb().expect("mesh has positions");
+    let mesh = world.resource_mut::<Assets<Mesh>>().add(mesh);
+    world
+        .spawn((
+            Mesh3d(mesh),
+            transform,
+            GlobalTransform::from(transform),
+            aabb,
+            Visibility::Visible,
+            InheritedVisibility::VISIBLE,
+            ViewVisibility::default(),
+            LidarInteractable,
+        ))
+        .id()
+}
+
+/// the box and ground plane from `level::setup_scene`, with only `lidar_new_points` running
+fn scene_app(shots_per_frame: usize) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(TimeUpdateStrategy::ManualDuration(
+            std::time::Duration::from_secs_f32(1.0 / 60.0),
+        ))
+        .insert_resource(Assets::<Mesh>::default())
+        .add_plugins(BvhPlugin)
+        .init_resource::<DiagnosticsStore>()
+        .add_event::<LidarShotFired>()
+        .add_event::<LidarShotResolved>()
+        .insert_resource(Space {
+            accelerator: VecStorage::new(POINT_LIMIT),
+        })
+        .insert_resource(GameSettings {
+            max_shots_per_frame: shots_per_frame as u32,
+            ..default()
+        })
+        .insert_resource(UnsavedScan::default())
+        .insert_resource(LidarRng::seeded(0))
+        .insert_resource(LidarNoise::default())
+        .insert_resource(MissLog::default())
+        .insert_resource(PendingShots::default())
+        .insert_resource(ShotBudget::default())
+        .add_systems(Update, lidar_new_points::<VecStorage>);
+
+    let world = app.world_mut();
+    spawn_mesh(
+        world,
+        Cuboid::new(5.0, 2.0, 5.0).into(),
+        Transform::from_xyz(0.0, 2.0, 0.0),
+    );
+    let plane = spawn_mesh(
+        world,
+        Plane3d::default()
+            .mesh()
+            .size(50.0, 50.0)
+            .subdivisions(5)
+            .build(),
+        Transform::from_xyz(0.0, 2.0, 0.0),
+    );
+    world.entity_mut(plane).insert(LidarReflectivity(0.5));
+    app
+}
+
+/// shots from a fixed spot aimed at the box, most hit and some graze past into the distance
+fn shots(count: usize) -> Vec<LidarShotFired> {
+    let mut rng = StdRng::seed_from_u64(count as u64);
+    let origin = Vec3::new(-12.0, 4.0, 0.0);
+    // ScanPattern::sample aims along local x, like the player. tilted down towards the box.
+    let transform = Transform::from_translation(origin).with_rotation(Quat::from_rotation_z(-0.15));
+    (0..count)
+        .map(|_| LidarShotFired {
+            origin,
+            direction: Dir3::new(ScanPattern::Cone.sample(&mut rng, SPREAD, &transform))
+                .expect("sampled a valid direction"),
+            source: 0,
+            max_range: MAX_RANGE,
+        })
+        .collect()
+}
+
+fn lidar_new_points_throughput(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lidar_new_points");
+    for &count in SHOTS_PER_FRAME {
+        let mut app = scene_app(count);
+        let shots = shots(count);
+        // warm up until the storage is about full, so frames trim like a long session would
+        for _ in 0..POINT_LIMIT.div_ceil(count) {
+            app.world_mut().send_event_batch(shots.iter().copied());
+            app.update();
+        }
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_function(BenchmarkId::from_parameter(count), |b| {
+            b.iter(|| {
+                app.world_mut().send_event_batch(shots.iter().copied());
+                app.update();
+            });
+        });
+    }
+    group.finish();
+}
+
+/// the rays of `shots`, cast one at a time through each structure and in parallel through the bvh
+fn scene_queries(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scene_query");
+    for &count in SHOTS_PER_FRAME {
+        let mut app = scene_app(count);
+        // builds the bvh
+        app.update();
+        let rays: Vec<(Ray3d, f32)> = shots(count)
+            .iter()
+            .map(|shot| (Ray3d::new(shot.origin, shot.direction), shot.max_range))
+            .collect();
+        group.throughput(Throughput::Elements(count as u64));
+
+        let world = app.world_mut();
+        let mut mesh_ray_cast =
+            SystemState::<(MeshRayCast, Query<(), With<LidarInteractable>>)>::new(world);
+        group.bench_function(BenchmarkId::new("mesh_ray_cast", count), |b| {
+            b.iter(|| {
+                let (mut ray_cast, interactable) = mesh_ray_cast.get_mut(world);
+                let filter = |entity| interactable.contains(entity);
+                let settings = RayCastSettings::default()
+                    .with_visibility(RayCastVisibility::Visible)
+                    .with_filter(&filter)
+                    .always_early_exit();
+                rays.iter()
+                    .filter(|&&(ray, max_distance)| {
+                        ray_cast
+                            .cast_ray(ray, &settings)
+                            .first()
+                            .is_some_and(|(_, hit)| hit.distance <= max_distance)
+                    })
+                    .count()
+            });
+        });
+
+        let bvh = world.resource::<SceneBvh>();
+        group.bench_function(BenchmarkId::new("bvh", count), |b| {
+            b.iter(|| {
+                rays.iter()
+                    .filter(|&&(ray, max_distance)| bvh.cast_ray(ray, max_distance).is_some())
+                    .count()
+            });
+        });
+        group.bench_function(BenchmarkId::new("bvh_parallel", count), |b| {
+            b.iter(|| bvh.cast_rays(&rays));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lidar_new_points_throughput, scene_queries);
+criterion_main!(benches);
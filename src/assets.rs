@@ -9,7 +9,10 @@ use serde::Serialize;
 // use bevy_kira_audio::AudioSource;
 
 use crate::{
+    cli::Cli,
     gamestate::GameState,
+    paths::{shipped_asset, GamePaths},
+    scanner::{ScannerHandles, ScannerProfile, SCANNER_PROFILES},
     settings::{GameSettings, UserSettings},
 };
 
@@ -24,12 +27,48 @@ impl AssetsTracking {
     }
 }
 
-const USER_CONFIG_FILE: &'static str = "user.ron";
-const GAME_CONFIG_FILE: &'static str = "game.rconfig";
+pub const USER_CONFIG_FILE: &'static str = "user.ron";
+pub const GAME_CONFIG_FILE: &'static str = "game.rconfig";
+
+/// handles to the settings files, wherever they ended up being loaded from
+#[derive(Resource)]
+pub struct SettingsHandles {
+    pub user: Handle<UserSettings>,
+    pub game: Handle<GameSettings>,
+}
+
+/// present once the settings files have been copied into `UserSettings` and `GameSettings`.
+/// before that they only hold defaults, which must not be written over the player's files.
+#[derive(Resource)]
+pub struct SettingsLoaded;
+
+fn write_ron_to<T: Serialize>(file: std::fs::File, value: &T) {
+    let mut serializer = Serializer::new(file, Some(PrettyConfig::new().depth_limit(4)))
+        .expect("couldn't create serializer");
+    let result = value.serialize(&mut serializer);
+    result.expect("could not write to file");
+}
+
+/// serializes the default value of `T` to `path`, doing nothing if the file already exists.
+pub fn write_default<T: Default + Serialize>(path: &Path) {
+    if let Ok(file) = std::fs::File::create_new(path) {
+        // will error if the file already exists
+        write_ron_to(file, &T::default());
+    }
+}
+
+/// serializes `value` to `path`, overwriting whatever was there.
+pub fn write_settings<T: Serialize>(path: &Path, value: &T) -> std::io::Result<()> {
+    let file = std::fs::File::create(path)?;
+    write_ron_to(file, value);
+    Ok(())
+}
 
 pub fn load_assets(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    paths: Res<GamePaths>,
+    cli: Res<Cli>,
     mut loading: ResMut<AssetsTracking>,
 ) {
     // pngs
@@ -54,28 +93,60 @@ pub fn load_assets(
     //     }));
     // }
 
-    let path = Path::new("assets").join(USER_CONFIG_FILE);
-    if let Ok(file) = std::fs::File::create_new(path) {
-        // will error if the file already exists
-        let mut serializer = Serializer::new(file, Some(PrettyConfig::new().depth_limit(4)))
-            .expect("couldn't create serializer");
-        let result = UserSettings::default().serialize(&mut serializer);
-        result.expect("could not write to file");
+    info!(
+        "using config dir {:?}, data dir {:?}",
+        paths.config_dir, paths.data_dir
+    );
+    if let Err(e) = paths.ensure_dirs() {
+        error!(
+            "could not create user directories at {:?} and {:?} - {}",
+            paths.config_dir, paths.data_dir, e
+        );
     }
 
-    let path = Path::new("assets").join(GAME_CONFIG_FILE);
-    if let Ok(file) = std::fs::File::create_new(path) {
-        // will error if the file already exists
-        let mut serializer = Serializer::new(file, Some(PrettyConfig::new().depth_limit(4)))
-            .expect("couldn't create serializer");
-        let result = GameSettings::default().serialize(&mut serializer);
-        result.expect("could not write to file");
+    // user settings always live in the config dir. seed them from the shipped defaults on first run.
+    // on the web the config dir is the shipped assets, which already have them.
+    let user_config_path = paths.config_dir.join(USER_CONFIG_FILE);
+    if cfg!(not(target_arch = "wasm32")) && !user_config_path.exists() {
+        match shipped_asset(USER_CONFIG_FILE) {
+            Some(shipped) => {
+                if let Err(e) = std::fs::copy(&shipped, &user_config_path) {
+                    error!("could not copy default user settings - {}", e);
+                }
+            }
+            None => write_default::<UserSettings>(&user_config_path),
+        }
     }
 
-    let handle: Handle<UserSettings> = asset_server.load(USER_CONFIG_FILE);
-    loading.add(handle.untyped());
-    let handle: Handle<GameSettings> = asset_server.load(GAME_CONFIG_FILE);
-    loading.add(handle.untyped());
+    // game config is read from the shipped assets, unless the user has placed an override in the config dir
+    // or passed one on the command line.
+    let game_config_path = paths.config_dir.join(GAME_CONFIG_FILE);
+    let game_config_asset_path = if let Some(asset_path) = cli.settings_asset_path() {
+        asset_path
+    } else if game_config_path.exists() {
+        GamePaths::config_asset_path(GAME_CONFIG_FILE)
+    } else if shipped_asset(GAME_CONFIG_FILE).is_some() {
+        GAME_CONFIG_FILE.to_string()
+    } else {
+        write_default::<GameSettings>(&game_config_path);
+        GamePaths::config_asset_path(GAME_CONFIG_FILE)
+    };
+
+    let user: Handle<UserSettings> =
+        asset_server.load(GamePaths::config_asset_path(USER_CONFIG_FILE));
+    loading.add(user.clone().untyped());
+    let game: Handle<GameSettings> = asset_server.load(game_config_asset_path);
+    loading.add(game.clone().untyped());
+    commands.insert_resource(SettingsHandles { user, game });
+
+    let scanners: Vec<Handle<ScannerProfile>> = SCANNER_PROFILES
+        .iter()
+        .map(|path| asset_server.load(*path))
+        .collect();
+    for handle in &scanners {
+        loading.add(handle.clone().untyped());
+    }
+    commands.insert_resource(ScannerHandles(scanners));
     info!("loading {} items", loading.0.len());
 }
 
@@ -106,11 +177,14 @@ pub fn loading_state_watcher<T: Asset>(
 }
 
 pub fn loading_update(
+    mut commands: Commands,
     mut game_config: ResMut<GameSettings>,
     mut user_config: ResMut<UserSettings>,
     mut state: ResMut<NextState<GameState>>,
     server: Res<AssetServer>,
     loading: Res<AssetsTracking>,
+    handles: Res<SettingsHandles>,
+    cli: Res<Cli>,
     user_config_asset: Res<Assets<UserSettings>>,
     game_config_asset: Res<Assets<GameSettings>>,
 ) {
@@ -138,24 +212,19 @@ pub fn loading_update(
     }
     if all_done {
         *user_config = user_config_asset
-            .get(
-                server
-                    .get_handle(USER_CONFIG_FILE)
-                    .expect("didn't find userconfig file handle in asset server")
-                    .id(),
-            )
+            .get(&handles.user)
             .expect("didn't find userconfig struct in asset server")
             .clone();
         *game_config = game_config_asset
-            .get(
-                server
-                    .get_handle(GAME_CONFIG_FILE)
-                    .expect("didn't find config file handle in asset server")
-                    .id(),
-            )
+            .get(&handles.game)
             .expect("didn't find config struct in asset server")
             .clone();
+        commands.insert_resource(SettingsLoaded);
 
-        state.set(GameState::MainMenu);
+        state.set(if cli.quickstart {
+            GameState::InGame
+        } else {
+            GameState::MainMenu
+        });
     }
 }
@@ -0,0 +1,340 @@
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
+        view::VisibilitySystems,
+    },
+    tasks::{ComputeTaskPool, ParallelSlice},
+    transform::TransformSystem,
+    utils::HashSet,
+};
+
+use crate::space::LidarInteractable;
+
+/// leaves hold at most this many triangles
+const MAX_LEAF_TRIANGLES: usize = 4;
+/// rays are cast in chunks of this many per task, smaller batches are cast on the calling thread
+const RAYS_PER_TASK: usize = 64;
+
+/// a world space triangle of a `LidarInteractable` mesh
+#[derive(Copy, Clone, Debug)]
+struct Triangle {
+    vertices: [Vec3; 3],
+    entity: Entity,
+}
+
+impl Triangle {
+    fn centroid(&self) -> Vec3 {
+        (self.vertices[0] + self.vertices[1] + self.vertices[2]) / 3.0
+    }
+
+    /// distance along the ray to the front face of the triangle, backfaces are culled like `MeshRayCast` does
+    fn intersect(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<f32> {
+        let [a, b, c] = self.vertices;
+        let (ab, ac) = (b - a, c - a);
+        let p = direction.cross(ac);
+        let determinant = ab.dot(p);
+        if determinant < f32::EPSILON {
+            return None;
+        }
+        let inverse = determinant.recip();
+        let to_origin = origin - a;
+        let u = to_origin.dot(p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = to_origin.cross(ab);
+        let v = direction.dot(q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let distance = ac.dot(q) * inverse;
+        (distance > 0.0 && distance <= max_distance).then_some(distance)
+    }
+
+    fn normal(&self) -> Vec3 {
+        let [a, b, c] = self.vertices;
+        (b - a).cross(c - a).normalize_or_zero()
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Node {
+    min: Vec3,
+    max: Vec3,
+    /// leaves: index of the first triangle. interior nodes: index of the second child, the first directly follows.
+    index: u32,
+    /// number of triangles, 0 for interior nodes
+    count: u32,
+}
+
+impl Node {
+    /// distance along the ray to where it enters the box, if it does within `max_distance`
+    fn entry(&self, origin: Vec3, inverse_direction: Vec3, max_distance: f32) -> Option<f32> {
+        let t0 = (self.min - origin) * inverse_direction;
+        let t1 = (self.max - origin) * inverse_direction;
+        let near = t0.min(t1).max_element().max(0.0);
+        let far = t0.max(t1).min_element().min(max_distance);
+        (near <= far).then_some(near)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct BvhHit {
+    pub entity: Entity,
+    pub distance: f32,
+    /// geometric normal of the triangle that was hit, facing the ray
+    pub normal: Vec3,
+}
+
+/// bounding volume hierarchy over the triangles of every visible `LidarInteractable` mesh, in world space.
+/// lets `space::lidar_new_points` cast its rays in parallel instead of going through `MeshRayCast` one at a time,
+/// and keeps the player out of the level, see `player::player_movement_system`.
+#[derive(Resource, Default)]
+pub struct SceneBvh {
+    triangles: Vec<Triangle>,
+    nodes: Vec<Node>,
+}
+
+impl SceneBvh {
+    pub fn from_meshes<'a>(
+        meshes: impl IntoIterator<Item = (Entity, &'a Mesh, &'a GlobalTransform)>,
+    ) -> Self {
+        let mut triangles = Vec::new();
+        for (entity, mesh, transform) in meshes {
+            mesh_triangles(entity, mesh, transform, &mut triangles);
+        }
+        let mut bvh = SceneBvh {
+            nodes: Vec::with_capacity(2 * triangles.len() / MAX_LEAF_TRIANGLES + 1),
+            triangles,
+        };
+        if !bvh.triangles.is_empty() {
+            bvh.split(0, bvh.triangles.len());
+        }
+        bvh
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+
+    /// builds the subtree over `triangles[start..end]`, splitting at the median centroid along the widest axis
+    fn split(&mut self, start: usize, end: usize) -> usize {
+        let triangles = &mut self.triangles[start..end];
+        let (min, max) = triangles
+            .iter()
+            .flat_map(|triangle| triangle.vertices)
+            .fold(
+                (Vec3::INFINITY, Vec3::NEG_INFINITY),
+                |(min, max), vertex| (min.min(vertex), max.max(vertex)),
+            );
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            min,
+            max,
+            index: start as u32,
+            count: triangles.len() as u32,
+        });
+        if triangles.len() <= MAX_LEAF_TRIANGLES {
+            return node;
+        }
+
+        let (centroid_min, centroid_max) = triangles.iter().map(Triangle::centroid).fold(
+            (Vec3::INFINITY, Vec3::NEG_INFINITY),
+            |(min, max), centroid| (min.min(centroid), max.max(centroid)),
+        );
+        let axis = (centroid_max - centroid_min).max_position();
+        let middle = triangles.len() / 2;
+        triangles.select_nth_unstable_by(middle, |a, b| {
+            a.centroid()[axis].total_cmp(&b.centroid()[axis])
+        });
+
+        self.split(start, start + middle);
+        let right = self.split(start + middle, end);
+        self.nodes[node].index = right as u32;
+        self.nodes[node].count = 0;
+        node
+    }
+
+    /// nearest front facing hit along the ray, no further than `max_distance`
+    pub fn cast_ray(&self, ray: Ray3d, max_distance: f32) -> Option<BvhHit> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let origin = ray.origin;
+        let direction = *ray.direction;
+        let inverse_direction = direction.recip();
+        let mut nearest: Option<(f32, &Triangle)> = None;
+        let mut limit = max_distance;
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0usize);
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.entry(origin, inverse_direction, limit).is_none() {
+                continue;
+            }
+            if node.count > 0 {
+                let start = node.index as usize;
+                for triangle in &self.triangles[start..start + node.count as usize] {
+                    if let Some(distance) = triangle.intersect(origin, direction, limit) {
+                        limit = distance;
+                        nearest = Some((distance, triangle));
+                    }
+                }
+                continue;
+            }
+            // visit the nearer child first, so the further one can often be skipped
+            let (left, right) = (index + 1, node.index as usize);
+            let left_entry = self.nodes[left].entry(origin, inverse_direction, limit);
+            let right_entry = self.nodes[right].entry(origin, inverse_direction, limit);
+            match (left_entry, right_entry) {
+                (Some(l), Some(r)) if l <= r => stack.extend([right, left]),
+                (Some(_), Some(_)) => stack.extend([left, right]),
+                (Some(_), None) => stack.push(left),
+                (None, Some(_)) => stack.push(right),
+                (None, None) => {}
+            }
+        }
+        nearest.map(|(distance, triangle)| BvhHit {
+            entity: triangle.entity,
+            distance,
+            normal: triangle.normal(),
+        })
+    }
+
+    /// casts every ray across the compute task pool. results are in the same order as `rays`.
+    pub fn cast_rays(&self, rays: &[(Ray3d, f32)]) -> Vec<Option<BvhHit>> {
+        if rays.len() <= RAYS_PER_TASK {
+            return rays
+                .iter()
+                .map(|&(ray, max_distance)| self.cast_ray(ray, max_distance))
+                .collect();
+        }
+        rays.par_chunk_map(ComputeTaskPool::get(), RAYS_PER_TASK, |_, chunk| {
+            chunk
+                .iter()
+                .map(|&(ray, max_distance)| self.cast_ray(ray, max_distance))
+                .collect::<Vec<_>>()
+        })
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+/// appends the world space triangles of a triangle list mesh
+fn mesh_triangles(
+    entity: Entity,
+    mesh: &Mesh,
+    transform: &GlobalTransform,
+    triangles: &mut Vec<Triangle>,
+) {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        warn!("{entity} isn't a triangle list, lidar can't hit it");
+        return;
+    }
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        warn!("{entity} has no vertex positions, lidar can't hit it");
+        return;
+    };
+    let affine = transform.affine();
+    let positions: Vec<Vec3> = positions
+        .iter()
+        .map(|&position| affine.transform_point3(position.into()))
+        .collect();
+    let triangle = |[a, b, c]: [usize; 3]| Triangle {
+        vertices: [positions[a], positions[b], positions[c]],
+        entity,
+    };
+    match mesh.indices() {
+        Some(Indices::U16(indices)) => triangles.extend(
+            indices
+                .chunks_exact(3)
+                .map(|i| triangle([i[0] as usize, i[1] as usize, i[2] as usize])),
+        ),
+        Some(Indices::U32(indices)) => triangles.extend(
+            indices
+                .chunks_exact(3)
+                .map(|i| triangle([i[0] as usize, i[1] as usize, i[2] as usize])),
+        ),
+        None => triangles
+            .extend((0..positions.len() / 3).map(|i| triangle([3 * i, 3 * i + 1, 3 * i + 2]))),
+    }
+}
+
+/// rebuilds the `SceneBvh` when `LidarInteractable`s are added, moved, hidden, removed or change meshes,
+/// once their transforms and visibility are up to date
+pub fn build_scene_bvh(
+    mut bvh: ResMut<SceneBvh>,
+    changed: Query<
+        (),
+        (
+            With<LidarInteractable>,
+            Or<(
+                Added<LidarInteractable>,
+                Changed<GlobalTransform>,
+                Changed<Mesh3d>,
+                Changed<InheritedVisibility>,
+            )>,
+        ),
+    >,
+    mut removed: RemovedComponents<LidarInteractable>,
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    targets: Query<
+        (Entity, &Mesh3d, &GlobalTransform, &InheritedVisibility),
+        With<LidarInteractable>,
+    >,
+    meshes: Res<Assets<Mesh>>,
+) {
+    let modified: HashSet<AssetId<Mesh>> = mesh_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } | AssetEvent::LoadedWithDependencies { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    let mesh_modified = !modified.is_empty()
+        && targets
+            .iter()
+            .any(|(_, mesh, _, _)| modified.contains(&mesh.id()));
+    if changed.is_empty() && removed.read().count() == 0 && !mesh_modified {
+        return;
+    }
+
+    *bvh = SceneBvh::from_meshes(targets.iter().filter_map(
+        |(entity, mesh, transform, visibility)| {
+            if !visibility.get() {
+                return None;
+            }
+            let Some(mesh) = meshes.get(mesh) else {
+                warn!("mesh of {entity} isn't loaded, lidar can't hit it");
+                return None;
+            };
+            Some((entity, mesh, transform))
+        },
+    ));
+    info!("built lidar bvh over {} triangles", bvh.triangle_count());
+}
+
+/// keeps the `SceneBvh` that lidar shots are cast against
+pub struct BvhPlugin;
+
+impl Plugin for BvhPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SceneBvh::default())
+            // already there with the `AssetPlugin`, but not in headless test apps
+            .add_event::<AssetEvent<Mesh>>()
+            .add_systems(
+                PostUpdate,
+                build_scene_bvh
+                    .after(TransformSystem::TransformPropagate)
+                    .after(VisibilitySystems::VisibilityPropagate),
+            );
+    }
+}
@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+
+use bevy::{
+    prelude::*,
+    window::{MonitorSelection, WindowMode, WindowResolution},
+};
+use clap::{Parser, ValueEnum};
+
+use crate::{
+    settings::GameSettings,
+    space::{PointStorage, Space},
+};
+
+/// name of the asset source that reads the directory containing the `--settings` file
+pub const SETTINGS_ASSET_SOURCE: &'static str = "cli";
+
+/// which `PointStorage` implementation backs `Space`
+#[derive(ValueEnum, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum StorageBackend {
+    /// flat ring buffer of points, oldest points are trimmed first
+    #[default]
+    Vec,
+    /// sparse octree, drawn with distance based level of detail
+    Octree,
+}
+
+/// launch options. anything set here takes precedence over the settings files.
+#[derive(Parser, Resource, Clone, Debug, Default)]
+#[command(version, about = "lidar scanning prototype")]
+pub struct Cli {
+    /// level to load
+    #[arg(long)]
+    pub level: Option<String>,
+    /// seed for all gameplay randomness, random if not given
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// game config file to use instead of `game.rconfig`
+    #[arg(long)]
+    pub settings: Option<PathBuf>,
+    /// point storage backend
+    #[arg(long, value_enum, default_value_t)]
+    pub storage: StorageBackend,
+    /// maximum number of points kept in the scene
+    #[arg(long)]
+    pub points_limit: Option<usize>,
+    /// run without a window or renderer
+    #[arg(long)]
+    pub headless: bool,
+    /// window width, in logical pixels
+    #[arg(long)]
+    pub width: Option<f32>,
+    /// window height, in logical pixels
+    #[arg(long)]
+    pub height: Option<f32>,
+    #[arg(long)]
+    pub fullscreen: bool,
+    /// resume a saved session
+    #[arg(long)]
+    pub load: Option<PathBuf>,
+    /// skip the main menu and go straight into the game
+    #[arg(long)]
+    pub quickstart: bool,
+    /// overrides the directory user settings are read from
+    #[arg(long)]
+    pub config_dir: Option<PathBuf>,
+    /// overrides the directory saved scans and replays are written to
+    #[arg(long)]
+    pub data_dir: Option<PathBuf>,
+}
+
+impl Cli {
+    pub fn primary_window(&self) -> Option<Window> {
+        if self.headless {
+            return None;
+        }
+        let mut window = Window {
+            title: "lidar".into(),
+            ..default()
+        };
+        if let (Some(width), Some(height)) = (self.width, self.height) {
+            window.resolution = WindowResolution::new(width, height);
+        } else if self.width.is_some() || self.height.is_some() {
+            warn!("both --width and --height are required to set the window size");
+        }
+        if self.fullscreen {
+            window.mode = WindowMode::BorderlessFullscreen(MonitorSelection::Current);
+        }
+        Some(window)
+    }
+
+    /// asset path of the `--settings` file, as seen through [`SETTINGS_ASSET_SOURCE`]
+    pub fn settings_asset_path(&self) -> Option<String> {
+        let file_name = self.settings.as_ref()?.file_name()?;
+        Some(format!(
+            "{}://{}",
+            SETTINGS_ASSET_SOURCE,
+            file_name.to_string_lossy()
+        ))
+    }
+
+    /// directory the `--settings` file lives in, which [`SETTINGS_ASSET_SOURCE`] is rooted at
+    pub fn settings_dir(&self) -> Option<PathBuf> {
+        let settings = self.settings.as_ref()?;
+        Some(match settings.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        })
+    }
+
+    pub fn apply(&self, game_settings: &mut GameSettings) {
+        if let Some(level) = &self.level {
+            game_settings.level = Some(level.clone());
+        }
+        if let Some(seed) = self.seed {
+            game_settings.seed = Some(seed);
+        }
+        if let Some(points_limit) = self.points_limit {
+            game_settings.points_limit = Some(points_limit);
+        }
+    }
+}
+
+/// applies launch options on top of the freshly loaded settings files
+pub fn apply_launch_options<S: PointStorage + Send + Sync + 'static>(
+    cli: Res<Cli>,
+    mut game_settings: ResMut<GameSettings>,
+    mut space: ResMut<Space<S>>,
+) {
+    cli.apply(&mut game_settings);
+    if let Some(limit) = game_settings.points_limit {
+        space.accelerator.set_limit(limit);
+    }
+}
@@ -0,0 +1,170 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    diagnostic::{
+        Diagnostic, DiagnosticPath, Diagnostics, DiagnosticsStore, LogDiagnosticsPlugin,
+        RegisterDiagnostic,
+    },
+    ecs::system::{lifetimeless::SRes, SystemParam},
+    prelude::*,
+};
+use iyes_perf_ui::{entry::PerfUiEntry, prelude::*, utils::next_sort_key};
+
+use crate::space::LidarTag;
+
+pub const RAYS_CAST: DiagnosticPath = DiagnosticPath::const_new("lidar/rays_cast");
+pub const HITS: DiagnosticPath = DiagnosticPath::const_new("lidar/hits");
+pub const MISSES: DiagnosticPath = DiagnosticPath::const_new("lidar/misses");
+pub const SHOTS_PENDING: DiagnosticPath = DiagnosticPath::const_new("lidar/shots_pending");
+pub const SHOTS_DROPPED: DiagnosticPath = DiagnosticPath::const_new("lidar/shots_dropped");
+pub const SHOTS_ALLOWED: DiagnosticPath = DiagnosticPath::const_new("lidar/shots_allowed");
+pub const SHOT_TIME: DiagnosticPath = DiagnosticPath::const_new("lidar/shot_time");
+pub const POINTS_ADDED: DiagnosticPath = DiagnosticPath::const_new("lidar/points_added");
+pub const POINTS_MERGED: DiagnosticPath = DiagnosticPath::const_new("lidar/points_merged");
+pub const POINTS_TRIMMED: DiagnosticPath = DiagnosticPath::const_new("lidar/points_trimmed");
+pub const POINT_ENTITIES: DiagnosticPath = DiagnosticPath::const_new("lidar/point_entities");
+
+/// a lidar diagnostic shown in the perf ui
+pub trait LidarDiagnostic: Send + Sync + 'static {
+    const PATH: DiagnosticPath;
+    const LABEL: &'static str;
+    const SUFFIX: &'static str = "";
+    const PRECISION: usize = 0;
+}
+
+macro_rules! lidar_diagnostic {
+    ($name:ident, $path:ident, $label:literal) => {
+        lidar_diagnostic!($name, $path, $label, "", 0);
+    };
+    ($name:ident, $path:ident, $label:literal, $suffix:literal, $precision:literal) => {
+        pub struct $name;
+        impl LidarDiagnostic for $name {
+            const PATH: DiagnosticPath = $path;
+            const LABEL: &'static str = $label;
+            const SUFFIX: &'static str = $suffix;
+            const PRECISION: usize = $precision;
+        }
+    };
+}
+
+lidar_diagnostic!(RaysCast, RAYS_CAST, "Rays/frame");
+lidar_diagnostic!(Hits, HITS, "Hits/frame");
+lidar_diagnostic!(Misses, MISSES, "Misses/frame");
+lidar_diagnostic!(ShotsPending, SHOTS_PENDING, "Pending Shots");
+lidar_diagnostic!(ShotsDropped, SHOTS_DROPPED, "Dropped Shots/frame");
+lidar_diagnostic!(ShotsAllowed, SHOTS_ALLOWED, "Shots Allowed/frame");
+lidar_diagnostic!(ShotTime, SHOT_TIME, "Shot Time", " ms", 2);
+lidar_diagnostic!(PointsAdded, POINTS_ADDED, "Points Added/frame");
+lidar_diagnostic!(PointsMerged, POINTS_MERGED, "Points Merged/frame");
+lidar_diagnostic!(PointsTrimmed, POINTS_TRIMMED, "Points Trimmed/frame");
+lidar_diagnostic!(PointEntities, POINT_ENTITIES, "Point Entities");
+
+/// perf ui entry showing the smoothed value of a `LidarDiagnostic`
+#[derive(Component)]
+pub struct PerfUiEntryLidar<D: LidarDiagnostic> {
+    pub sort_key: i32,
+    _diagnostic: PhantomData<fn() -> D>,
+}
+
+impl<D: LidarDiagnostic> Default for PerfUiEntryLidar<D> {
+    fn default() -> Self {
+        Self {
+            sort_key: next_sort_key(),
+            _diagnostic: PhantomData,
+        }
+    }
+}
+
+impl<D: LidarDiagnostic> PerfUiEntry for PerfUiEntryLidar<D> {
+    type Value = f64;
+    type SystemParam = SRes<DiagnosticsStore>;
+
+    fn label(&self) -> &str {
+        D::LABEL
+    }
+
+    fn sort_key(&self) -> i32 {
+        self.sort_key
+    }
+
+    fn update_value(
+        &self,
+        diagnostics: &mut <Self::SystemParam as SystemParam>::Item<'_, '_>,
+    ) -> Option<Self::Value> {
+        diagnostics.get(&D::PATH)?.smoothed()
+    }
+
+    fn format_value(&self, value: &Self::Value) -> String {
+        format!("{:.*}{}", D::PRECISION, value, D::SUFFIX)
+    }
+}
+
+/// every lidar entry, spawn it next to `PerfUiDefaultEntries`
+#[derive(Bundle, Default)]
+pub struct PerfUiLidarEntries {
+    rays_cast: PerfUiEntryLidar<RaysCast>,
+    hits: PerfUiEntryLidar<Hits>,
+    misses: PerfUiEntryLidar<Misses>,
+    shots_pending: PerfUiEntryLidar<ShotsPending>,
+    shots_dropped: PerfUiEntryLidar<ShotsDropped>,
+    shots_allowed: PerfUiEntryLidar<ShotsAllowed>,
+    shot_time: PerfUiEntryLidar<ShotTime>,
+    points_added: PerfUiEntryLidar<PointsAdded>,
+    points_merged: PerfUiEntryLidar<PointsMerged>,
+    points_trimmed: PerfUiEntryLidar<PointsTrimmed>,
+    point_entities: PerfUiEntryLidar<PointEntities>,
+}
+
+/// the perf ui with the default and lidar entries
+pub fn setup_perf_ui(mut commands: Commands) {
+    commands.spawn((PerfUiDefaultEntries::default(), PerfUiLidarEntries::default()));
+}
+
+fn count_point_entities(mut diagnostics: Diagnostics, points: Query<(), With<LidarTag>>) {
+    diagnostics.add_measurement(&POINT_ENTITIES, || points.iter().count() as f64);
+}
+
+/// registers the lidar diagnostics. most are measured in `space::lidar_new_points`.
+pub struct LidarDiagnosticsPlugin {
+    /// log the diagnostics, for when there's no perf ui to look at
+    pub log: bool,
+}
+
+impl Plugin for LidarDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        let paths = [
+            RAYS_CAST,
+            HITS,
+            MISSES,
+            SHOTS_PENDING,
+            SHOTS_DROPPED,
+            SHOTS_ALLOWED,
+            SHOT_TIME,
+            POINTS_ADDED,
+            POINTS_MERGED,
+            POINTS_TRIMMED,
+            POINT_ENTITIES,
+        ];
+        for path in &paths {
+            app.register_diagnostic(Diagnostic::new(path.clone()));
+        }
+        app.add_systems(Update, count_point_entities)
+            .add_perf_ui_simple_entry::<PerfUiEntryLidar<RaysCast>>()
+            .add_perf_ui_simple_entry::<PerfUiEntryLidar<Hits>>()
+            .add_perf_ui_simple_entry::<PerfUiEntryLidar<Misses>>()
+            .add_perf_ui_simple_entry::<PerfUiEntryLidar<ShotsPending>>()
+            .add_perf_ui_simple_entry::<PerfUiEntryLidar<ShotsDropped>>()
+            .add_perf_ui_simple_entry::<PerfUiEntryLidar<ShotsAllowed>>()
+            .add_perf_ui_simple_entry::<PerfUiEntryLidar<ShotTime>>()
+            .add_perf_ui_simple_entry::<PerfUiEntryLidar<PointsAdded>>()
+            .add_perf_ui_simple_entry::<PerfUiEntryLidar<PointsMerged>>()
+            .add_perf_ui_simple_entry::<PerfUiEntryLidar<PointsTrimmed>>()
+            .add_perf_ui_simple_entry::<PerfUiEntryLidar<PointEntities>>();
+        if self.log {
+            app.add_plugins(LogDiagnosticsPlugin {
+                filter: Some(paths.to_vec()),
+                ..default()
+            });
+        }
+    }
+}
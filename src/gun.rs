@@ -1,9 +1,12 @@
 use bevy::{ prelude::*};
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    gamestate::InGameState,
     input::{FiringMode, PlayerInput},
-    settings::GameSettings,
-    util::solid_angle_sample,
+    scanner::{switch_scanner, sync_scanner_colors, ScanPattern, ScannerProfile},
+    settings::{GameSettings, GunHeatSettings},
+    util::LidarRng,
 };
 
 use std::f32::consts::{FRAC_PI_2, PI};
@@ -12,35 +15,118 @@ use std::f32::consts::{FRAC_PI_2, PI};
 pub struct LidarShotFired {
     pub origin: Vec3,
     pub direction: Dir3,
+    /// `LidarGun::source` of the gun that fired
+    pub source: u32,
+    /// `LidarGun::max_range` of the gun that fired
+    pub max_range: f32,
 }
-#[derive(Component)]
 
+/// sent for every `LidarShotFired` once it has been raycast
+#[derive(Event, Copy, Clone, Debug)]
+pub struct LidarShotResolved {
+    pub origin: Vec3,
+    pub direction: Dir3,
+    /// where the shot hit, `None` for misses
+    pub hit: Option<Vec3>,
+    pub max_range: f32,
+}
+
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub struct LidarGun {
     // radians from center of the aim direction to the maximal extent of the spread
     pub current_angular_spread_radius: f32,
+    /// fire rate once fully spun up
     pub fire_rate_per_second: f32,
-    // TODO: add max_fire_rate and a better charge up mechanic.
-    // currently,
-    // pub max_fire_rate: f32,
     saved_time_secs: f32,
+    /// how far the gun has spun up towards its full fire rate, from 0 to 1
+    #[serde(default)]
+    pub spin: f32,
+    /// from 0 to 1, the gun overheats at 1
+    #[serde(default)]
+    pub heat: f32,
+    /// set when the gun overheats, the gun can't fire until it cools down to `GunHeatSettings::recover_heat`
+    #[serde(default)]
+    pub overheated: bool,
+    /// identifies this gun's points, so they can be colored differently
+    #[serde(default)]
+    pub source: u32,
+    /// hits further away than this are misses
+    pub max_range: f32,
+    #[serde(default)]
+    pub pattern: ScanPattern,
 }
 
 impl LidarGun {
-    pub fn new(angular_spread: f32, fire_rate: f32) -> Self {
+    pub fn new(angular_spread: f32, fire_rate: f32, max_range: f32) -> Self {
         Self {
             current_angular_spread_radius: angular_spread,
             fire_rate_per_second: fire_rate,
             saved_time_secs: 0.0,
+            spin: 0.0,
+            heat: 0.0,
+            overheated: false,
+            source: 0,
+            max_range,
+            pattern: ScanPattern::Cone,
+        }
+    }
+
+    /// a gun configured from a scanner profile, `source` being its slot in the player's `Loadout`.
+    /// the profile's fire rate and range win over `GameSettings::gun_fire_rate` and `gun_max_range`.
+    pub fn from_profile(profile: &ScannerProfile, source: u32) -> Self {
+        Self {
+            source,
+            pattern: profile.pattern,
+            ..Self::new(profile.spread, profile.fire_rate, profile.max_range)
         }
     }
-    pub fn charge(&mut self, time: f32) {
+
+    /// fraction of `fire_rate_per_second` the gun currently fires at
+    pub fn rate_fraction(&self, heat: &GunHeatSettings) -> f32 {
+        heat.min_rate_fraction + (1.0 - heat.min_rate_fraction) * self.spin
+    }
+
+    pub fn current_fire_rate(&self, heat: &GunHeatSettings) -> f32 {
+        self.fire_rate_per_second * self.rate_fraction(heat)
+    }
+
+    /// spins up and heats the gun while the trigger is held for `time` seconds
+    pub fn charge(&mut self, time: f32, heat: &GunHeatSettings) {
+        if self.overheated {
+            self.cool(time, heat);
+            return;
+        }
+        self.spin = (self.spin + time / heat.spin_up_secs.max(f32::EPSILON)).min(1.0);
+        if heat.overheat_secs > 0.0 {
+            self.heat += time * self.rate_fraction(heat) / heat.overheat_secs;
+        }
+        if self.heat >= 1.0 {
+            self.heat = 1.0;
+            self.overheated = true;
+            self.spin = 0.0;
+            self.saved_time_secs = 0.0;
+            return;
+        }
         self.saved_time_secs += time;
     }
 
-    pub fn shoot(&mut self) -> usize {
-        let num = (self.saved_time_secs * self.fire_rate_per_second).floor();
+    /// spins down and cools the gun while the trigger is released
+    pub fn cool(&mut self, time: f32, heat: &GunHeatSettings) {
+        self.spin = (self.spin - time / heat.spin_up_secs.max(f32::EPSILON)).max(0.0);
+        self.heat = (self.heat - time / heat.cooldown_secs.max(f32::EPSILON)).max(0.0);
+        if self.overheated && self.heat <= heat.recover_heat {
+            self.overheated = false;
+        }
+    }
+
+    pub fn shoot(&mut self, heat: &GunHeatSettings) -> usize {
+        let fire_rate = self.current_fire_rate(heat);
+        if fire_rate <= 0.0 {
+            return 0;
+        }
+        let num = (self.saved_time_secs * fire_rate).floor();
 
-        self.saved_time_secs -= num / self.fire_rate_per_second;
+        self.saved_time_secs -= num / fire_rate;
         self.saved_time_secs = self.saved_time_secs.max(0.0);
         num as usize
     }
@@ -50,10 +136,17 @@ pub fn lidar_basic_shot_system(
     mut query: Query<(&mut LidarGun, &Transform)>,
     time: Res<Time>,
     player_input: Res<PlayerInput>,
+    mut rng: ResMut<LidarRng>,
     mut shots: EventWriter<LidarShotFired>,
+    settings: Res<GameSettings>,
 ) {
+    let heat = &settings.gun_heat;
     match &player_input.firing_mode {
-        FiringMode::None => {}
+        FiringMode::None => {
+            if let Ok((mut lidar_data, _)) = query.get_single_mut() {
+                lidar_data.cool(time.delta_secs(), heat);
+            }
+        }
         FiringMode::Firing => {
 
             let delta = time.delta_secs();
@@ -61,18 +154,24 @@ pub fn lidar_basic_shot_system(
             let Ok((mut lidar_data, transform)) = query.get_single_mut() else {
                 return;
             };
-            lidar_data.charge(delta);
+            lidar_data.charge(delta, heat);
             let origin = transform.translation;
             // could use base_direction, left, and up instead of compute_matrix and transform_vector3
             // let base_direction = transform.forward();
 
-            for _ in 0..lidar_data.shoot() {
+            for _ in 0..lidar_data.shoot(heat) {
                 // sample and send event
-                let dir = solid_angle_sample(lidar_data.current_angular_spread_radius);
+                let dir = lidar_data.pattern.sample(
+                    &mut **rng,
+                    lidar_data.current_angular_spread_radius,
+                    transform,
+                );
                 shots.send(LidarShotFired {
                     origin,
-                    direction: Dir3::new(transform.compute_matrix().transform_vector3(dir.zxy()))
+                    direction: Dir3::new(dir)
                         .expect("failed to construct direction from sample, should not happen"),
+                    source: lidar_data.source,
+                    max_range: lidar_data.max_range,
                 });
             }
         }
@@ -100,3 +199,19 @@ pub fn lidar_spread_sync(
         }
     }
 }
+
+/// firing, spread and scanner switching for the player's `LidarGun`. shots are resolved by `space::SpacePlugin`.
+pub struct GunPlugin;
+
+impl Plugin for GunPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LidarShotFired>()
+            .add_event::<LidarShotResolved>()
+            .add_systems(
+                Update,
+                (lidar_basic_shot_system, lidar_spread_sync, switch_scanner)
+                    .run_if(in_state(InGameState::Active)),
+            )
+            .add_systems(Update, sync_scanner_colors);
+    }
+}
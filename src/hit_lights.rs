@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    gamestate::GameState,
+    settings::{GameSettings, HitLightMode, HitLightSettings},
+    space::LidarPoint,
+};
+
+/// hard cap on the number of hit lights, whatever the settings say
+pub const MAX_HIT_LIGHTS: usize = 64;
+/// lights sit this far off the surface along its normal, a light right on the surface doesn't light it
+const SURFACE_OFFSET: f32 = 0.25;
+
+#[derive(Component)]
+pub struct HitLight;
+
+/// where a hit light goes
+struct LightTarget {
+    position: Vec3,
+    normal: Vec3,
+    /// multiplier on `HitLightSettings::intensity`
+    weight: f32,
+}
+
+/// recently scanned points, and the pool of light entities reused every frame
+#[derive(Resource, Default)]
+pub struct HitLights {
+    recent: VecDeque<Entity>,
+    pool: Vec<Entity>,
+}
+
+fn track_recent_points(
+    mut lights: ResMut<HitLights>,
+    settings: Res<GameSettings>,
+    new_points: Query<Entity, Added<LidarPoint>>,
+) {
+    let settings = &settings.hit_lights;
+    let window = match settings.mode {
+        HitLightMode::Off => 0,
+        HitLightMode::Recent => settings.max_lights.min(MAX_HIT_LIGHTS),
+        HitLightMode::Clustered => settings.cluster_window,
+    };
+    lights.recent.extend(new_points.iter());
+    let excess = lights.recent.len().saturating_sub(window);
+    lights.recent.drain(..excess);
+}
+
+/// the densest cubes of recent points, densest first
+fn cluster_targets(points: &[LidarPoint], settings: &HitLightSettings) -> Vec<LightTarget> {
+    let mut clusters: HashMap<IVec3, (usize, LightTarget)> = HashMap::default();
+    for point in points {
+        let key = (point.position / settings.cluster_size.max(0.01))
+            .floor()
+            .as_ivec3();
+        let (count, target) = clusters.entry(key).or_insert((
+            0,
+            LightTarget {
+                position: Vec3::ZERO,
+                normal: Vec3::ZERO,
+                weight: 0.0,
+            },
+        ));
+        *count += 1;
+        target.position += point.position;
+        target.normal += point.normal;
+        target.weight += point.intensity;
+    }
+
+    let mut clusters: Vec<(usize, LightTarget)> = clusters.into_values().collect();
+    clusters.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+    // the densest cluster is as bright as its average point, the rest scale down with their size
+    let densest = clusters.first().map_or(1, |(count, _)| *count) as f32;
+    clusters
+        .into_iter()
+        .map(|(count, target)| LightTarget {
+            position: target.position / count as f32,
+            normal: target.normal.normalize_or_zero(),
+            weight: target.weight / densest,
+        })
+        .collect()
+}
+
+fn update_hit_lights(
+    mut commands: Commands,
+    mut lights: ResMut<HitLights>,
+    settings: Res<GameSettings>,
+    points: Query<&LidarPoint>,
+    mut pool: Query<(&mut PointLight, &mut Transform, &mut Visibility), With<HitLight>>,
+) {
+    let settings = &settings.hit_lights;
+    let count = match settings.mode {
+        HitLightMode::Off => 0,
+        _ => settings.max_lights.min(MAX_HIT_LIGHTS),
+    };
+
+    let recent: Vec<LidarPoint> = points.iter_many(&lights.recent).copied().collect();
+    let mut targets = match settings.mode {
+        HitLightMode::Off => vec![],
+        HitLightMode::Recent => recent
+            .iter()
+            .rev()
+            .map(|point| LightTarget {
+                position: point.position,
+                normal: point.normal,
+                weight: point.intensity,
+            })
+            .collect(),
+        HitLightMode::Clustered => cluster_targets(&recent, settings),
+    };
+    targets.truncate(count);
+
+    while lights.pool.len() < count {
+        let entity = commands
+            .spawn((
+                HitLight,
+                PointLight::default(),
+                Transform::IDENTITY,
+                Visibility::Hidden,
+            ))
+            .id();
+        lights.pool.push(entity);
+    }
+    for entity in lights.pool.drain(count..) {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    // lights spawned this frame get placed on the next one
+    for (i, entity) in lights.pool.iter().enumerate() {
+        let Ok((mut light, mut transform, mut visibility)) = pool.get_mut(*entity) else {
+            continue;
+        };
+        match targets.get(i) {
+            Some(target) => {
+                transform.translation = target.position + target.normal * SURFACE_OFFSET;
+                light.intensity = settings.intensity * target.weight;
+                light.range = settings.range;
+                *visibility = Visibility::Visible;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+/// lights the level around recent lidar hits, see `settings::HitLightSettings`
+pub struct HitLightPlugin;
+
+impl Plugin for HitLightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HitLights>().add_systems(
+            Update,
+            (track_recent_points, update_hit_lights)
+                .chain()
+                .run_if(in_state(GameState::InGame)),
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    gamestate::GameState,
+    gun::LidarGun,
+    player::Player,
+    scanner::{Loadout, ScanPattern, ScannerProfile},
+    settings::GameSettings,
+    space::{PointStorage, Space},
+};
+
+const HEAT_COOL_COLOR: LinearRgba = LinearRgba::rgb(0.1, 0.3, 1.0);
+const HEAT_HOT_COLOR: LinearRgba = LinearRgba::rgb(1.0, 0.1, 0.02);
+const OVERHEATED_COLOR: Color = Color::srgb(1.0, 0.0, 0.0);
+const HUD_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.6);
+
+/// fill of the heat meter, its width follows `LidarGun::heat`
+#[derive(Component)]
+struct HeatMeter;
+
+/// ring around the crosshair showing where shots can land
+#[derive(Component)]
+struct SpreadRing;
+
+#[derive(Component)]
+struct GunText;
+
+#[derive(Component)]
+pub struct PointBudgetText;
+
+fn setup_hud(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(24.0),
+                left: Val::Percent(40.0),
+                width: Val::Percent(20.0),
+                height: Val::Px(8.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(1.0, 1.0, 1.0, 0.1)),
+        ))
+        .with_children(|meter| {
+            meter.spawn((
+                HeatMeter,
+                Node {
+                    width: Val::Percent(0.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                BackgroundColor(HEAT_COOL_COLOR.into()),
+            ));
+        });
+
+    // crosshair, with the spread ring centered on it
+    commands
+        .spawn(Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        })
+        .with_children(|screen| {
+            screen.spawn((
+                SpreadRing,
+                Node {
+                    position_type: PositionType::Absolute,
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                BorderColor(HUD_COLOR),
+                BorderRadius::MAX,
+            ));
+            screen.spawn((
+                Node {
+                    width: Val::Px(4.0),
+                    height: Val::Px(4.0),
+                    ..default()
+                },
+                BackgroundColor(HUD_COLOR),
+                BorderRadius::MAX,
+            ));
+        });
+
+    commands
+        .spawn(Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(16.0),
+            left: Val::Px(16.0),
+            flex_direction: FlexDirection::Column,
+            ..default()
+        })
+        .with_children(|panel| {
+            panel.spawn((GunText, Text::default(), TextColor(HUD_COLOR)));
+            panel.spawn((PointBudgetText, Text::default(), TextColor(HUD_COLOR)));
+        });
+}
+
+fn update_heat_meter(
+    gun: Query<&LidarGun, (With<Player>, Changed<LidarGun>)>,
+    mut meter: Query<(&mut Node, &mut BackgroundColor), With<HeatMeter>>,
+) {
+    let (Ok(gun), Ok((mut node, mut color))) = (gun.get_single(), meter.get_single_mut()) else {
+        return;
+    };
+    node.width = Val::Percent(gun.heat * 100.0);
+    color.0 = if gun.overheated {
+        OVERHEATED_COLOR
+    } else {
+        HEAT_COOL_COLOR.mix(&HEAT_HOT_COLOR, gun.heat).into()
+    };
+}
+
+/// sizes the spread ring to the spread as seen through the camera
+fn update_spread_ring(
+    gun: Query<&LidarGun, With<Player>>,
+    camera: Query<&Projection, With<Camera3d>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut ring: Query<&mut Node, With<SpreadRing>>,
+) {
+    let (Ok(gun), Ok(Projection::Perspective(projection)), Ok(window), Ok(mut ring)) = (
+        gun.get_single(),
+        camera.get_single(),
+        window.get_single(),
+        ring.get_single_mut(),
+    ) else {
+        return;
+    };
+
+    let spread = gun.current_angular_spread_radius.min(1.5);
+    let radius = spread.tan() / (projection.fov / 2.0).tan() * window.height() / 2.0;
+    let (width, height, display) = match gun.pattern {
+        ScanPattern::Cone => (radius * 2.0, radius * 2.0, Display::Flex),
+        ScanPattern::Line => (radius * 2.0, 0.0, Display::Flex),
+        ScanPattern::Rangefinder | ScanPattern::Spinner => (0.0, 0.0, Display::None),
+    };
+    ring.width = Val::Px(width);
+    ring.height = Val::Px(height);
+    ring.display = display;
+}
+
+fn update_gun_text(
+    gun: Query<(&LidarGun, &Loadout), (With<Player>, Changed<LidarGun>)>,
+    profiles: Res<Assets<ScannerProfile>>,
+    settings: Res<GameSettings>,
+    mut text: Query<&mut Text, With<GunText>>,
+) {
+    let (Ok((gun, loadout)), Ok(mut text)) = (gun.get_single(), text.get_single_mut()) else {
+        return;
+    };
+    let name = loadout
+        .scanners
+        .get(gun.source as usize)
+        .and_then(|handle| profiles.get(handle))
+        .map_or("scanner", |profile| profile.name.as_str());
+    let status = if gun.overheated {
+        "OVERHEATED".to_string()
+    } else {
+        format!(
+            "{:.0} / {:.0} shots/s, {:.0}% spun up",
+            gun.current_fire_rate(&settings.gun_heat),
+            gun.fire_rate_per_second,
+            gun.spin * 100.0
+        )
+    };
+    text.0 = format!(
+        "[{}] {} ({:?})\n{}",
+        gun.source + 1,
+        name,
+        gun.pattern,
+        status
+    );
+}
+
+pub fn update_point_budget<S: PointStorage + Send + Sync + 'static>(
+    space: Res<Space<S>>,
+    mut text: Query<&mut Text, With<PointBudgetText>>,
+) {
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+    if !space.is_changed() && !text.0.is_empty() {
+        return;
+    }
+    text.0 = format!(
+        "{} / {} points",
+        space.accelerator.len(),
+        space.accelerator.limit()
+    );
+}
+
+/// in game overlay. `update_point_budget` depends on the point storage, so it's added alongside `Space`.
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::InGame), setup_hud)
+            .add_systems(
+                Update,
+                (update_heat_meter, update_spread_ring, update_gun_text)
+                    .run_if(in_state(GameState::InGame)),
+            );
+    }
+}
@@ -1,9 +1,12 @@
 use std::f32::consts::TAU;
 
 /// unifies mouse input and gamepad input
-use bevy::{input::mouse::MouseMotion, prelude::*};
+use bevy::{
+    input::mouse::{MouseMotion, MouseWheel},
+    prelude::*,
+};
 
-use crate::settings::UserSettings;
+use crate::{gamestate::InGameState, settings::UserSettings};
 
 #[derive(Default, Clone, Debug)]
 pub enum FiringMode {
@@ -31,11 +34,28 @@ pub struct PlayerInput {
     pub firing_mode: FiringMode,
     pub fire_trigger: PressedStatus,
     pub burst_trigger: PressedStatus,
+    /// loadout slot picked with the number keys this frame
+    pub scanner_select: Option<usize>,
+    /// loadout slots to step through this frame, from the scroll wheel
+    pub scanner_cycle: i32,
 }
 
+const SCANNER_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
 pub fn player_input_system(
     mut player_input: ResMut<PlayerInput>,
     mut mouse_movements: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
     mouse_button_input: Res<ButtonInput<MouseButton>>,
     keyboard: Res<ButtonInput<KeyCode>>,
     // gamepads: Query<(&Name, &Gamepad)>,
@@ -80,6 +100,19 @@ pub fn player_input_system(
     player_input.elevation = elevation;
     player_input.gun_spread_intent = gun_spread_intent;
 
+    player_input.scanner_select = SCANNER_KEYS
+        .iter()
+        .position(|key| keyboard.just_pressed(*key));
+    let scroll: f32 = mouse_wheel.read().map(|wheel| wheel.y).sum();
+    // scrolling down moves to the next scanner
+    player_input.scanner_cycle = if scroll < 0.0 {
+        1
+    } else if scroll > 0.0 {
+        -1
+    } else {
+        0
+    };
+
     if move_direction.length_squared() > 0.0 {
         move_direction = move_direction.normalize();
     }
@@ -144,3 +177,19 @@ pub fn player_firing_sync(mut player_input: ResMut<PlayerInput>) {
         PressedStatus::Held => {}
     }
 }
+
+pub struct PlayerInputPlugin;
+
+impl Plugin for PlayerInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PlayerInput::default())
+            .add_systems(
+                PreUpdate,
+                player_input_system.run_if(in_state(InGameState::Active)),
+            )
+            .add_systems(
+                Update,
+                player_firing_sync.run_if(in_state(InGameState::Active)),
+            );
+    }
+}
@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+use bevy::render::{
+    render_asset::RenderAssetUsages,
+    render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{
+    settings::{GameSettings, HitLightMode},
+    space::{LidarInteractable, LidarReflectivity},
+    util::LidarRng,
+};
+
+/// the only level that exists so far, a box sitting on a plane
+pub const DEFAULT_LEVEL: &'static str = "test_box";
+
+/// level and seed of the current session
+#[derive(Resource, Clone, Debug)]
+pub struct CurrentLevel {
+    pub name: String,
+    pub seed: u64,
+}
+
+/// picks the level and seeds the rng, once settings and launch options are known
+pub fn select_level(mut commands: Commands, settings: Res<GameSettings>) {
+    let name = settings
+        .level
+        .clone()
+        .unwrap_or_else(|| DEFAULT_LEVEL.to_string());
+    let seed = settings.seed.unwrap_or_else(rand::random);
+    info!("level {} with seed {}", name, seed);
+
+    commands.insert_resource(LidarRng::seeded(seed));
+    commands.insert_resource(CurrentLevel { name, seed });
+}
+
+/// Creates a colorful test pattern
+fn uv_debug_texture() -> Image {
+    const TEXTURE_SIZE: usize = 8;
+
+    let mut palette: [u8; 32] = [
+        255, 102, 159, 255, 255, 159, 102, 255, 236, 255, 102, 255, 121, 255, 102, 255, 102, 255,
+        198, 255, 102, 198, 255, 255, 121, 102, 255, 255, 236, 102, 255, 255,
+    ];
+
+    let mut texture_data = [0; TEXTURE_SIZE * TEXTURE_SIZE * 4];
+    for y in 0..TEXTURE_SIZE {
+        let offset = TEXTURE_SIZE * y * 4;
+        texture_data[offset..(offset + TEXTURE_SIZE * 4)].copy_from_slice(&palette);
+        palette.rotate_right(4);
+    }
+
+    Image::new_fill(
+        Extent3d {
+            width: TEXTURE_SIZE as u32,
+            height: TEXTURE_SIZE as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &texture_data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+/// spawns the level geometry
+pub fn setup_scene(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>, // textures
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    ass: Res<AssetServer>,
+    level: Res<CurrentLevel>,
+    settings: Res<GameSettings>,
+) {
+    if level.name != DEFAULT_LEVEL {
+        warn!("unknown level {}, loading {} instead", level.name, DEFAULT_LEVEL);
+    }
+
+    // note that we have to include the `Scene0` label
+    // let scene_gltf = ass.load("main.glb#Scene0");
+
+    // if we swap to using scenes, they'll need to be preprocessed somehow to get all the materials to be completely Transparent with AlphaBlend set to Add
+    // maybe also somehow pre-adding the LidarInteractable component
+    // commands
+    //     .spawn(SceneBundle {
+    //         scene: scene_gltf,
+    //         transform: Transform::from_xyz(0.0, 0.0, 0.0),
+    //         ..Default::default()
+    //     })
+    //     .insert(LidarInteractable);
+
+    let completely_transparent_material = if settings.hit_lights.mode == HitLightMode::Off {
+        materials.add(StandardMaterial {
+            base_color: Color::srgba(0.0, 0.0, 0.0, 0.0),
+            alpha_mode: AlphaMode::Add,
+            ..default()
+        })
+    } else {
+        // with no ambient light, this only adds to the image where hit lights reach it
+        commands.insert_resource(AmbientLight {
+            brightness: 0.0,
+            ..default()
+        });
+        materials.add(StandardMaterial {
+            base_color: Color::WHITE,
+            perceptual_roughness: 1.0,
+            alpha_mode: AlphaMode::Add,
+            ..default()
+        })
+    };
+
+    let shape = meshes.add(Cuboid::new(5.0, 2.0, 5.0));
+    let actual_material = completely_transparent_material;
+
+    commands
+        .spawn((
+            Mesh3d(shape),
+            MeshMaterial3d(actual_material.clone()),
+            Visibility::Visible,
+            Transform::from_xyz(0.0, 2.0, 0.0),
+        ))
+        .insert(LidarInteractable);
+
+    let plane = meshes.add(
+        Plane3d::default()
+            .mesh()
+            .size(50.0, 50.0)
+            .subdivisions(5)
+            .build(),
+    );
+
+    commands
+        .spawn((
+            Mesh3d(plane),
+            MeshMaterial3d(actual_material.clone()),
+            Visibility::Visible,
+            Transform::from_xyz(0.0, 2.0, 0.0),
+        ))
+        .insert((LidarInteractable, LidarReflectivity(0.5)));
+}
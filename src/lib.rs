@@ -0,0 +1,198 @@
+//! everything but the app setup, which lives in main.rs.
+//! other bevy apps can embed the game by adding `LidarPlugin`, see main.rs for an example.
+
+pub mod assets;
+pub mod bvh;
+pub mod cli;
+pub mod diagnostics;
+pub mod gamestate;
+pub mod gun;
+pub mod hit_lights;
+pub mod hud;
+pub mod input;
+pub mod level;
+pub mod lod;
+pub mod material;
+pub mod noise;
+pub mod octree;
+pub mod paths;
+pub mod pause;
+pub mod player;
+pub mod point_cloud;
+pub mod quit;
+pub mod save;
+pub mod scanner;
+pub mod settings;
+pub mod space;
+pub mod tracers;
+pub mod util;
+
+use std::time::Duration;
+
+use bevy::{
+    asset::io::{AssetSource, AssetSourceBuilder},
+    diagnostic::FrameTimeDiagnosticsPlugin,
+    prelude::*,
+};
+use iyes_perf_ui::PerfUiPlugin;
+
+use cli::{apply_launch_options, Cli, StorageBackend, SETTINGS_ASSET_SOURCE};
+use diagnostics::{setup_perf_ui, LidarDiagnosticsPlugin};
+use gamestate::{dummy_mainmenu, game_ending_system, GameEndingTimer, GameState};
+use gun::GunPlugin;
+use hit_lights::HitLightPlugin;
+use hud::{update_point_budget, HudPlugin};
+use input::PlayerInputPlugin;
+use level::{select_level, setup_scene};
+use lod::LodPlugin;
+use material::PointMaterialPlugin;
+use octree::OctreeStorage;
+use pause::PausePlugin;
+use paths::{GamePaths, CONFIG_ASSET_SOURCE};
+use player::PlayerPlugin;
+use point_cloud::PointCloudPlugin;
+use quit::QuitPlugin;
+use save::{
+    load_session, load_session_from_cli, poll_pending_saves, save_session, session_hotkeys,
+    LoadSession, PendingSaves, SaveSession, UnsavedScan,
+};
+use settings::SettingsPlugin;
+use space::{LidarTag, PointStorage, SpacePlugin, VecStorage};
+use tracers::TracerPlugin;
+use util::LidarRng;
+
+/// the whole game. needs the `DefaultPlugins`, and `register_asset_sources` called before them.
+pub struct LidarPlugin {
+    /// launch options, `Cli::storage` picks the point storage backend
+    pub cli: Cli,
+    pub paths: GamePaths,
+    /// initial point limit, overridden by points_limit from game_config or the command line
+    pub points_limit: usize,
+    /// only used with `StorageBackend::Octree`
+    pub octree_leaf_size: f32,
+    /// adds the perf ui and frame time diagnostics. turn off if the app already has them.
+    pub perf_ui: bool,
+}
+
+impl LidarPlugin {
+    pub fn new(cli: Cli) -> Self {
+        let paths = GamePaths::resolve(cli.config_dir.clone(), cli.data_dir.clone());
+        Self {
+            cli,
+            paths,
+            points_limit: 80000,
+            octree_leaf_size: octree::DEFAULT_LEAF_SIZE,
+            perf_ui: true,
+        }
+    }
+
+    /// the config dir, and the directory of the `--settings` file. has to happen before the `AssetPlugin` is added.
+    pub fn register_asset_sources(&self, app: &mut App) {
+        app.register_asset_source(
+            CONFIG_ASSET_SOURCE,
+            AssetSourceBuilder::default().with_reader(AssetSource::get_default_reader(
+                self.paths.config_dir.to_string_lossy().into_owned(),
+            )),
+        );
+        if let Some(settings_dir) = self.cli.settings_dir() {
+            app.register_asset_source(
+                SETTINGS_ASSET_SOURCE,
+                AssetSourceBuilder::default().with_reader(AssetSource::get_default_reader(
+                    settings_dir.to_string_lossy().into_owned(),
+                )),
+            );
+        }
+    }
+}
+
+impl Default for LidarPlugin {
+    fn default() -> Self {
+        Self::new(Cli::default())
+    }
+}
+
+impl Plugin for LidarPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.paths.clone())
+            .insert_resource(self.cli.clone())
+            // headless runs have no perf ui, so log the lidar diagnostics instead
+            .add_plugins(LidarDiagnosticsPlugin {
+                log: self.cli.headless,
+            })
+            // game state
+            .insert_state::<GameState>(GameState::Loading)
+            .add_plugins((
+                SettingsPlugin,
+                PointMaterialPlugin,
+                PlayerInputPlugin,
+                PlayerPlugin,
+                GunPlugin,
+                PausePlugin,
+                HitLightPlugin,
+                TracerPlugin,
+                HudPlugin,
+            ))
+            // misc events and resources
+            .add_event::<SaveSession>()
+            .add_event::<LoadSession>()
+            .insert_resource(PendingSaves::default())
+            .insert_resource(UnsavedScan::default())
+            .insert_resource(GameEndingTimer(Timer::new(
+                Duration::from_millis(500),
+                TimerMode::Once,
+            )))
+            .insert_resource(LidarRng::default())
+            // systems
+            .add_systems(
+                OnEnter(GameState::InGame),
+                (setup_scene, load_session_from_cli),
+            )
+            .add_systems(
+                Update,
+                (dummy_mainmenu).run_if(in_state(GameState::MainMenu)),
+            )
+            .add_systems(Update, session_hotkeys.run_if(in_state(GameState::InGame)))
+            .add_systems(Update, poll_pending_saves)
+            .add_systems(
+                Update,
+                game_ending_system::<LidarTag>.run_if(in_state(GameState::GameEnding)),
+            );
+
+        if self.perf_ui {
+            app.add_plugins(FrameTimeDiagnosticsPlugin)
+                .add_plugins(PerfUiPlugin)
+                .add_systems(OnEnter(GameState::InGame), setup_perf_ui);
+        }
+
+        match self.cli.storage {
+            StorageBackend::Vec => {
+                app.add_plugins(SpacePlugin {
+                    accelerator: VecStorage::new(self.points_limit),
+                })
+                .add_plugins(PointCloudPlugin);
+                add_storage_systems::<VecStorage>(app);
+            }
+            StorageBackend::Octree => {
+                app.add_plugins(SpacePlugin {
+                    accelerator: OctreeStorage::new(self.octree_leaf_size, self.points_limit),
+                })
+                .add_plugins(LodPlugin::<OctreeStorage>::default());
+                add_storage_systems::<OctreeStorage>(app);
+            }
+        }
+    }
+}
+
+/// everything outside of `SpacePlugin` that depends on the point storage backend
+fn add_storage_systems<S: PointStorage + Send + Sync + 'static>(app: &mut App) {
+    app.add_plugins(QuitPlugin::<S>::default())
+        .add_systems(
+            OnExit(GameState::Loading),
+            (apply_launch_options::<S>, select_level).chain(),
+        )
+        .add_systems(
+            Update,
+            (update_point_budget::<S>, save_session::<S>, load_session::<S>)
+                .run_if(in_state(GameState::InGame)),
+        );
+}
@@ -0,0 +1,194 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    pbr::NotShadowCaster,
+    prelude::*,
+    render::{primitives::Aabb, view::VisibilitySystems},
+    utils::HashMap,
+};
+
+use crate::{
+    point_cloud::PointTemplate,
+    settings::{GameSettings, LodSettings},
+    space::{LidarPoint, PointSummary, Space, SpatialPointStorage, SphereHandles},
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LodLevel {
+    /// every point in the leaf
+    Full,
+    /// every `LodSettings::mid_stride`th point
+    Decimated,
+    /// a single cube filling the leaf, brighter the more points it holds
+    Impostor,
+}
+
+impl LodLevel {
+    pub fn at_distance(settings: &LodSettings, distance: f32) -> Self {
+        if distance < settings.near_distance {
+            LodLevel::Full
+        } else if distance < settings.far_distance {
+            LodLevel::Decimated
+        } else {
+            LodLevel::Impostor
+        }
+    }
+}
+
+/// the mesh drawing one leaf of a `SpatialPointStorage`
+#[derive(Default)]
+pub struct LodChunk {
+    /// spawned when the chunk is first built
+    pub entity: Option<Entity>,
+    /// level the current mesh was built at
+    pub level: Option<LodLevel>,
+    /// the leaf's points changed since the mesh was built
+    pub stale: bool,
+}
+
+#[derive(Resource, Default)]
+pub struct LodChunks {
+    pub chunks: HashMap<IVec3, LodChunk>,
+}
+
+/// the point an impostor is drawn as, so it can share the point material
+fn impostor_point(
+    summary: &PointSummary,
+    center: Vec3,
+    volume: f32,
+    settings: &LodSettings,
+) -> Option<LidarPoint> {
+    let density = summary.count as f32 / volume;
+    let brightness = (density / settings.impostor_full_density).min(1.0);
+    summary.newest.map(|newest| LidarPoint {
+        position: center,
+        distance: summary.mean_distance(),
+        intensity: brightness * summary.mean_intensity(),
+        ..newest
+    })
+}
+
+/// rebuilds the chunks whose points changed or whose level of detail changed as the camera moved
+pub fn update_lod_chunks<S: SpatialPointStorage + Send + Sync + 'static>(
+    mut commands: Commands,
+    mut chunks: ResMut<LodChunks>,
+    mut space: ResMut<Space<S>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    handles: Res<SphereHandles>,
+    settings: Res<GameSettings>,
+    camera: Query<&GlobalTransform, With<Camera3d>>,
+    mut templates: Local<Option<(PointTemplate, PointTemplate)>>,
+) {
+    for key in space.accelerator.take_dirty_leaves() {
+        chunks.chunks.entry(key).or_default().stale = true;
+    }
+    if handles.is_changed() {
+        // the point shape changed
+        *templates = None;
+    }
+    if settings.is_changed() || handles.is_changed() {
+        for chunk in chunks.chunks.values_mut() {
+            chunk.stale = true;
+        }
+    }
+
+    let storage = &space.accelerator;
+    let leaf_size = storage.leaf_size();
+    let (Ok(camera), Some(material)) = (camera.get_single(), handles.material.clone()) else {
+        return;
+    };
+    if templates.is_none() {
+        let Some(point_mesh) = handles.mesh.as_ref().and_then(|handle| meshes.get(handle)) else {
+            return;
+        };
+        *templates = Some((
+            PointTemplate::new(point_mesh),
+            PointTemplate::fixed(&Mesh::from(Cuboid::from_length(leaf_size))),
+        ));
+    }
+    let Some((point_template, impostor_template)) = templates.as_ref() else {
+        return;
+    };
+
+    let mut emptied = Vec::new();
+    for (key, chunk) in chunks.chunks.iter_mut() {
+        let Some(summary) = storage.leaf_summary(*key) else {
+            emptied.push(*key);
+            continue;
+        };
+        let center = (key.as_vec3() + 0.5) * leaf_size;
+        let level = LodLevel::at_distance(&settings.lod, camera.translation().distance(center));
+        if !chunk.stale && chunk.level == Some(level) {
+            continue;
+        }
+        chunk.stale = false;
+        chunk.level = Some(level);
+
+        let leaf = storage.leaf(*key).iter().map(|(point, _)| *point);
+        let mesh = meshes.add(match level {
+            LodLevel::Full => point_template.build(&leaf.collect::<Vec<_>>()),
+            LodLevel::Decimated => point_template.build(
+                &leaf
+                    .step_by(settings.lod.mid_stride.max(1))
+                    .collect::<Vec<_>>(),
+            ),
+            LodLevel::Impostor => impostor_template.build(
+                &impostor_point(&summary, center, leaf_size.powi(3), &settings.lod)
+                    .into_iter()
+                    .collect::<Vec<_>>(),
+            ),
+        });
+        match chunk.entity {
+            Some(entity) => {
+                // same as in point_cloud, the aabb has to be recomputed for the new mesh
+                commands
+                    .entity(entity)
+                    .insert(Mesh3d(mesh))
+                    .remove::<Aabb>();
+            }
+            None => {
+                chunk.entity = Some(
+                    commands
+                        .spawn((
+                            Mesh3d(mesh),
+                            MeshMaterial3d(material.clone()),
+                            Transform::IDENTITY,
+                            Visibility::Visible,
+                            NotShadowCaster,
+                        ))
+                        .id(),
+                );
+            }
+        }
+    }
+
+    for key in emptied {
+        if let Some(entity) = chunks.chunks.remove(&key).and_then(|chunk| chunk.entity) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// draws the leaves of a `SpatialPointStorage` with distance based level of detail, instead of `point_cloud::PointCloudPlugin`
+pub struct LodPlugin<S> {
+    _storage: PhantomData<fn() -> S>,
+}
+
+impl<S> Default for LodPlugin<S> {
+    fn default() -> Self {
+        Self {
+            _storage: PhantomData,
+        }
+    }
+}
+
+impl<S: SpatialPointStorage + Send + Sync + 'static> Plugin for LodPlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LodChunks>().add_systems(
+            PostUpdate,
+            update_lod_chunks::<S>
+                .after(TransformSystem::TransformPropagate)
+                .before(VisibilitySystems::CalculateBounds),
+        );
+    }
+}
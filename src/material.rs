@@ -1,34 +1,96 @@
 use bevy::{
+    pbr::{MaterialPipeline, MaterialPipelineKey},
     prelude::*,
-    render::render_resource::{AsBindGroup, ShaderRef},
+    render::{
+        mesh::MeshVertexBufferLayoutRef,
+        render_resource::{
+            AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+        },
+    },
+};
+
+use crate::{
+    point_cloud::{ATTRIBUTE_POINT_DATA, ATTRIBUTE_POINT_OFFSET},
+    settings::{GameSettings, PointShape, PointSizeMode, UserSettings},
+    space::SphereHandles,
 };
 
 const SHADER_ASSET_PATH: &'static str = "shaders/custom_material.wgsl";
 
-#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+/// number of distinct colors available for `LidarPoint::source`
+pub const MAX_POINT_SOURCES: usize = 8;
 
+/// material shared by every point. per point variation comes from `ATTRIBUTE_POINT_DATA`.
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+#[bind_group_data(PointMaterialKey)]
 pub struct CustomMaterial {
+    /// color of points right next to the gun
+    #[uniform(0)]
+    pub near_color: LinearRgba,
+    /// color of points at `1 / color_distance_factor` and beyond
+    #[uniform(0)]
+    pub far_color: LinearRgba,
+    /// tint per `LidarPoint::source`
+    #[uniform(0)]
+    pub source_colors: [LinearRgba; MAX_POINT_SOURCES],
+    #[uniform(0)]
+    pub color_distance_factor: f32,
+    /// seconds for a point to fade out completely, 0 to never fade
+    #[uniform(0)]
+    pub fade_duration: f32,
+    /// brightness of the dimmest returns, so that weak returns stay visible
+    #[uniform(0)]
+    pub min_intensity: f32,
+    /// see `settings::PointStyle`
+    #[uniform(0)]
+    pub point_radius: f32,
+    #[uniform(0)]
+    pub point_pixels: f32,
     #[uniform(0)]
-    color: LinearRgba,
-    #[uniform(1)]
-    hue: f32,
+    pub point_attenuation: f32,
+    pub shape: PointShape,
+    pub size_mode: PointSizeMode,
+}
+
+/// shape and size mode are compiled into the shader rather than branched on
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct PointMaterialKey {
+    shape: PointShape,
+    size_mode: PointSizeMode,
+}
+
+impl From<&CustomMaterial> for PointMaterialKey {
+    fn from(material: &CustomMaterial) -> Self {
+        Self {
+            shape: material.shape,
+            size_mode: material.size_mode,
+        }
+    }
 }
 
 impl Default for CustomMaterial {
     fn default() -> Self {
         Self {
-            color: LinearRgba {
-                red: 1.0,
-                green: 1.0,
-                blue: 1.0,
-                alpha: 1.0,
-            },
-            hue: 1.0,
+            near_color: LinearRgba::WHITE,
+            far_color: LinearRgba::WHITE,
+            source_colors: [LinearRgba::WHITE; MAX_POINT_SOURCES],
+            color_distance_factor: 0.0,
+            fade_duration: 0.0,
+            min_intensity: 0.1,
+            point_radius: 0.1,
+            point_pixels: 4.0,
+            point_attenuation: 0.05,
+            shape: PointShape::Sphere,
+            size_mode: PointSizeMode::World,
         }
     }
 }
 
 impl Material for CustomMaterial {
+    fn vertex_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+
     fn fragment_shader() -> ShaderRef {
         SHADER_ASSET_PATH.into()
     }
@@ -36,4 +98,111 @@ impl Material for CustomMaterial {
     fn alpha_mode(&self) -> AlphaMode {
         AlphaMode::Add
     }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let vertex_layout = layout.0.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            ATTRIBUTE_POINT_DATA.at_shader_location(1),
+            ATTRIBUTE_POINT_OFFSET.at_shader_location(2),
+        ])?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+
+        let PointMaterialKey { shape, size_mode } = key.bind_group_data;
+        let mut shader_defs = vec![];
+        if shape != PointShape::Sphere {
+            shader_defs.push("POINT_BILLBOARD".into());
+        }
+        if matches!(shape, PointShape::Disc | PointShape::Splat) {
+            shader_defs.push("POINT_ROUND".into());
+        }
+        if shape == PointShape::Splat {
+            shader_defs.push("POINT_SPLAT".into());
+        } else if size_mode == PointSizeMode::Attenuated {
+            shader_defs.push("POINT_ATTENUATED".into());
+        }
+        descriptor.vertex.shader_defs.extend(shader_defs.iter().cloned());
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader_defs.extend(shader_defs);
+        }
+        Ok(())
+    }
+}
+
+/// shape each point is built from. vertices are offsets from the point, scaled by the point size in the shader.
+/// billboarded shapes lie in the xy plane, facing +z.
+pub fn point_shape_mesh(shape: PointShape) -> Mesh {
+    match shape {
+        PointShape::Sphere => Sphere::new(1.0).mesh().ico(1).unwrap(),
+        PointShape::Disc | PointShape::Quad | PointShape::Splat => Rectangle::new(2.0, 2.0).into(),
+    }
+}
+
+/// creates the shared point mesh and material
+pub fn setup_meshes(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<CustomMaterial>>,
+    mut sphere_handles: ResMut<SphereHandles>,
+) {
+    // replaced by update_point_material if the user settings pick another shape
+    let shape = meshes.add(point_shape_mesh(PointShape::default()));
+
+    let material = materials.add(CustomMaterial::default());
+    sphere_handles.mesh = Some(shape);
+    sphere_handles.material = Some(material);
+}
+
+/// keeps the point material in sync with the settings, and swaps the point mesh when the shape changes.
+/// point age comes from `globals.time` in the shader, so the material only changes along with the settings.
+pub fn update_point_material(
+    settings: Res<GameSettings>,
+    user_settings: Res<UserSettings>,
+    mut handles: ResMut<SphereHandles>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<CustomMaterial>>,
+) {
+    // taking the material mutably re-uploads it, even if nothing was written
+    if !settings.is_changed() && !user_settings.is_changed() {
+        return;
+    }
+    let Some(material) = handles
+        .material
+        .as_ref()
+        .and_then(|handle| materials.get_mut(handle))
+    else {
+        return;
+    };
+    if settings.is_changed() {
+        material.color_distance_factor = settings.color_distance_factor;
+        material.fade_duration = settings.point_fade_secs;
+    }
+    if user_settings.is_changed() {
+        let style = &user_settings.points;
+        let shape_changed = material.shape != style.shape;
+        material.point_radius = style.radius;
+        material.point_pixels = style.pixels;
+        material.point_attenuation = style.attenuation;
+        material.shape = style.shape;
+        material.size_mode = style.size_mode;
+        if shape_changed {
+            // chunks rebuild with the new shape once they see `SphereHandles` changed
+            handles.mesh = Some(meshes.add(point_shape_mesh(style.shape)));
+        }
+    }
+}
+
+/// the material and mesh every point is drawn with, see `space::SphereHandles`
+pub struct PointMaterialPlugin;
+
+impl Plugin for PointMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MaterialPlugin::<CustomMaterial>::default())
+            .insert_resource(SphereHandles::default())
+            .add_systems(Startup, setup_meshes)
+            .add_systems(Update, update_point_material);
+    }
 }
@@ -0,0 +1,82 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng};
+
+use crate::{
+    settings::{GameSettings, NoiseModelKind, NoiseSettings},
+    util::gaussian,
+};
+
+/// how the sensor deviates from a perfect measurement. applied by `space::lidar_new_points`.
+/// all randomness comes from the `util::LidarRng` passed in, so a seeded run stays reproducible.
+pub trait NoiseModel: Send + Sync {
+    /// direction the shot actually travels in
+    fn jitter_direction(&self, rng: &mut StdRng, direction: Dir3) -> Dir3;
+    /// whether a hit at this incidence angle produces a return
+    fn returns(&self, rng: &mut StdRng, incidence_angle: f32) -> bool;
+    /// range the sensor reports for a hit at `distance`
+    fn measure_range(&self, rng: &mut StdRng, distance: f32) -> f32;
+}
+
+pub struct Noiseless;
+
+impl NoiseModel for Noiseless {
+    fn jitter_direction(&self, _rng: &mut StdRng, direction: Dir3) -> Dir3 {
+        direction
+    }
+    fn returns(&self, _rng: &mut StdRng, _incidence_angle: f32) -> bool {
+        true
+    }
+    fn measure_range(&self, _rng: &mut StdRng, distance: f32) -> f32 {
+        distance
+    }
+}
+
+/// normally distributed range and direction errors, random dropout, and no returns at grazing angles
+pub struct GaussianNoise(pub NoiseSettings);
+
+impl NoiseModel for GaussianNoise {
+    fn jitter_direction(&self, rng: &mut StdRng, direction: Dir3) -> Dir3 {
+        if self.0.angular_sigma <= 0.0 {
+            return direction;
+        }
+        let (a, b) = direction.any_orthonormal_pair();
+        let offset = (a * gaussian(rng) + b * gaussian(rng)) * self.0.angular_sigma;
+        Dir3::new(*direction + offset).unwrap_or(direction)
+    }
+
+    fn returns(&self, rng: &mut StdRng, incidence_angle: f32) -> bool {
+        if incidence_angle > self.0.max_incidence_angle {
+            return false;
+        }
+        self.0.dropout <= 0.0 || rng.random::<f32>() >= self.0.dropout
+    }
+
+    fn measure_range(&self, rng: &mut StdRng, distance: f32) -> f32 {
+        (distance + gaussian(rng) * self.0.range_sigma * distance).max(0.0)
+    }
+}
+
+impl NoiseSettings {
+    pub fn model(&self) -> Box<dyn NoiseModel> {
+        match self.model {
+            NoiseModelKind::None => Box::new(Noiseless),
+            NoiseModelKind::Gaussian => Box::new(GaussianNoise(self.clone())),
+        }
+    }
+}
+
+/// the noise model in use
+#[derive(Resource)]
+pub struct LidarNoise(pub Box<dyn NoiseModel>);
+
+impl Default for LidarNoise {
+    fn default() -> Self {
+        LidarNoise(Box::new(Noiseless))
+    }
+}
+
+pub fn sync_noise_model(settings: Res<GameSettings>, mut noise: ResMut<LidarNoise>) {
+    if settings.is_changed() {
+        noise.0 = settings.noise.model();
+    }
+}
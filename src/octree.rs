@@ -0,0 +1,200 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::space::{LidarPoint, PointStorage, PointSummary, SpatialPointStorage, VoxelIndex};
+
+/// side length of the octree leaves, and so of the chunks drawn by `LodPlugin`
+pub const DEFAULT_LEAF_SIZE: f32 = 8.0;
+
+/// the leaf level of a sparse octree, stored in a hash map.
+/// leaves are cubes of `leaf_size` holding the points themselves, along with a `PointSummary` for far away impostors.
+/// nothing draws coarser than a leaf, so no levels above them are kept.
+/// trimming still drops the oldest points first, like `VecStorage`.
+#[derive(Clone)]
+pub struct OctreeStorage {
+    pub limit: usize,
+    leaf_size: f32,
+    leaves: HashMap<IVec3, Vec<(LidarPoint, Entity)>>,
+    /// index of each point within its leaf
+    slots: HashMap<Entity, usize>,
+    summaries: HashMap<IVec3, PointSummary>,
+    order: VecDeque<(LidarPoint, Entity)>,
+    dirty: HashSet<IVec3>,
+    voxels: VoxelIndex,
+}
+
+impl OctreeStorage {
+    pub fn new(leaf_size: f32, limit: usize) -> Self {
+        Self {
+            limit,
+            leaf_size,
+            leaves: HashMap::default(),
+            slots: HashMap::default(),
+            summaries: HashMap::default(),
+            order: VecDeque::new(),
+            dirty: HashSet::default(),
+            voxels: VoxelIndex::default(),
+        }
+    }
+
+    pub fn leaf_key(&self, position: Vec3) -> IVec3 {
+        (position / self.leaf_size).floor().as_ivec3()
+    }
+
+    fn insert(&mut self, point: LidarPoint, entity: Entity) {
+        let key = self.leaf_key(point.position);
+        let leaf = self.leaves.entry(key).or_default();
+        self.slots.insert(entity, leaf.len());
+        leaf.push((point, entity));
+        self.summaries.entry(key).or_default().add(&point);
+        self.dirty.insert(key);
+    }
+
+    fn remove(&mut self, point: &LidarPoint, entity: Entity) {
+        let key = self.leaf_key(point.position);
+        if let (Some(leaf), Some(index)) = (self.leaves.get_mut(&key), self.slots.remove(&entity)) {
+            leaf.swap_remove(index);
+            // the last point took the removed one's place
+            if let Some((_, moved)) = leaf.get(index) {
+                self.slots.insert(*moved, index);
+            }
+            if leaf.is_empty() {
+                self.leaves.remove(&key);
+            }
+        }
+        if let Some(summary) = self.summaries.get_mut(&key) {
+            summary.remove(point);
+            if summary.count == 0 {
+                self.summaries.remove(&key);
+            }
+        }
+        self.dirty.insert(key);
+    }
+}
+
+impl PointStorage for OctreeStorage {
+    fn add_points(&mut self, points: &[LidarPoint], entities: &[Entity]) {
+        for (point, entity) in points.iter().zip(entities.iter()) {
+            self.insert(*point, *entity);
+            self.order.push_back((*point, *entity));
+            self.voxels.push(point.position);
+        }
+    }
+    fn trim(&mut self) -> Vec<Entity> {
+        let mut trimmed = vec![];
+        while self.order.len() > self.limit {
+            let Some((point, entity)) = self.order.pop_front() else {
+                break;
+            };
+            if self.voxels.pop(point.position) {
+                self.voxels.push(point.position);
+                self.order.push_back((point, entity));
+                continue;
+            }
+            self.remove(&point, entity);
+            trimmed.push(entity);
+        }
+        trimmed
+    }
+    fn len(&self) -> usize {
+        self.order.len()
+    }
+    fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+    fn limit(&self) -> usize {
+        self.limit
+    }
+    fn points(&self) -> Vec<LidarPoint> {
+        self.order.iter().map(|(point, _)| *point).collect()
+    }
+    fn clear(&mut self) -> Vec<Entity> {
+        self.dirty.extend(self.leaves.keys().copied());
+        self.leaves.clear();
+        self.slots.clear();
+        self.summaries.clear();
+        self.voxels.clear();
+        self.order.drain(..).map(|(_, entity)| entity).collect()
+    }
+    fn query_aabb(&self, min: Vec3, max: Vec3) -> Vec<LidarPoint> {
+        let (min_key, max_key) = (self.leaf_key(min), self.leaf_key(max));
+        let span = (max_key - min_key + IVec3::ONE)
+            .max(IVec3::ZERO)
+            .as_i64vec3();
+        let contained = |point: &&(LidarPoint, Entity)| {
+            point.0.position.cmpge(min).all() && point.0.position.cmple(max).all()
+        };
+
+        // walk whichever is smaller, the leaves overlapping the box or all existing leaves
+        if span.x * span.y * span.z <= self.leaves.len() as i64 {
+            let mut result = vec![];
+            for x in min_key.x..=max_key.x {
+                for y in min_key.y..=max_key.y {
+                    for z in min_key.z..=max_key.z {
+                        if let Some(leaf) = self.leaves.get(&IVec3::new(x, y, z)) {
+                            result.extend(leaf.iter().filter(contained).map(|(p, _)| *p));
+                        }
+                    }
+                }
+            }
+            result
+        } else {
+            self.leaves
+                .iter()
+                .filter(|(key, _)| key.cmpge(min_key).all() && key.cmple(max_key).all())
+                .flat_map(|(_, leaf)| leaf.iter().filter(contained).map(|(p, _)| *p))
+                .collect()
+        }
+    }
+    fn voxel_size(&self) -> f32 {
+        self.voxels.size()
+    }
+    fn set_voxel_size(&mut self, size: f32) {
+        self.voxels
+            .resize(size, self.order.iter().map(|(point, _)| point.position));
+    }
+    fn merge(&mut self, point: &LidarPoint) -> Option<(LidarPoint, Entity)> {
+        let index = self.voxels.find(point.position)?;
+        let (old, entity) = self.order[index];
+        let mut refreshed = old;
+        refreshed.refresh(point);
+        self.order[index].0 = refreshed;
+        self.voxels.refresh(index);
+
+        // refreshed points don't move, so they stay in the same leaf
+        let key = self.leaf_key(old.position);
+        if let Some(stored) = self
+            .leaves
+            .get_mut(&key)
+            .zip(self.slots.get(&entity))
+            .and_then(|(leaf, &slot)| leaf.get_mut(slot))
+        {
+            stored.0 = refreshed;
+        }
+        if let Some(summary) = self.summaries.get_mut(&key) {
+            summary.remove(&old);
+            summary.add(&refreshed);
+        }
+        self.dirty.insert(key);
+        Some((refreshed, entity))
+    }
+}
+
+impl SpatialPointStorage for OctreeStorage {
+    fn leaf_size(&self) -> f32 {
+        self.leaf_size
+    }
+    fn take_dirty_leaves(&mut self) -> Vec<IVec3> {
+        self.dirty.drain().collect()
+    }
+    fn leaf(&self, key: IVec3) -> &[(LidarPoint, Entity)] {
+        self.leaves.get(&key).map(Vec::as_slice).unwrap_or(&[])
+    }
+    fn leaf_summary(&self, key: IVec3) -> Option<PointSummary> {
+        self.summaries.get(&key).copied()
+    }
+}
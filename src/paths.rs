@@ -0,0 +1,111 @@
+use std::path::PathBuf;
+
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+
+/// name of the asset source that reads from the user config directory, i.e. `config://user.ron`
+pub const CONFIG_ASSET_SOURCE: &'static str = "config";
+
+#[cfg(not(target_arch = "wasm32"))]
+const APP_DIR_NAME: &'static str = "lidar";
+#[cfg(not(target_arch = "wasm32"))]
+const CONFIG_DIR_ENV: &'static str = "LIDAR_CONFIG_DIR";
+#[cfg(not(target_arch = "wasm32"))]
+const DATA_DIR_ENV: &'static str = "LIDAR_DATA_DIR";
+
+/// where user owned files live.
+/// the shipped `assets/` directory is only ever read from, and is used for defaults.
+#[derive(Resource, Clone, Debug)]
+pub struct GamePaths {
+    /// user settings and overridden game config
+    pub config_dir: PathBuf,
+    /// saved scans, replays, and other generated data
+    pub data_dir: PathBuf,
+}
+
+impl GamePaths {
+    /// resolves directories in order of priority: explicit override (i.e. from a cli flag), environment variable, platform directory.
+    /// falls back to `assets/` if the platform has no notion of a config directory.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn resolve(config_override: Option<PathBuf>, data_override: Option<PathBuf>) -> Self {
+        let config_dir = config_override
+            .or_else(|| std::env::var_os(CONFIG_DIR_ENV).map(PathBuf::from))
+            .or_else(|| dirs::config_dir().map(|dir| dir.join(APP_DIR_NAME)))
+            .unwrap_or_else(shipped_assets_dir);
+        let data_dir = data_override
+            .or_else(|| std::env::var_os(DATA_DIR_ENV).map(PathBuf::from))
+            .or_else(|| dirs::data_dir().map(|dir| dir.join(APP_DIR_NAME)))
+            .unwrap_or_else(|| config_dir.clone());
+        Self {
+            config_dir,
+            data_dir,
+        }
+    }
+
+    /// the web has no user directories, everything is read from the served `assets/`.
+    #[cfg(target_arch = "wasm32")]
+    pub fn resolve(_config_override: Option<PathBuf>, _data_override: Option<PathBuf>) -> Self {
+        Self {
+            config_dir: shipped_assets_dir(),
+            data_dir: shipped_assets_dir(),
+        }
+    }
+
+    pub fn scans_dir(&self) -> PathBuf {
+        self.data_dir.join("scans")
+    }
+
+    pub fn replays_dir(&self) -> PathBuf {
+        self.data_dir.join("replays")
+    }
+
+    /// creates all the user directories, if they don't exist already.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn ensure_dirs(&self) -> std::io::Result<()> {
+        for dir in [
+            self.config_dir.clone(),
+            self.scans_dir(),
+            self.replays_dir(),
+        ] {
+            std::fs::create_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    /// nothing to create on the web, the served `assets/` is read only.
+    #[cfg(target_arch = "wasm32")]
+    pub fn ensure_dirs(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    /// asset path for a file in the config directory, loadable by the asset server.
+    pub fn config_asset_path(file: &str) -> String {
+        format!("{}://{}", CONFIG_ASSET_SOURCE, file)
+    }
+}
+
+/// the `assets/` directory as the asset server sees it.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn shipped_assets_dir() -> PathBuf {
+    FileAssetReader::get_base_path().join("assets")
+}
+
+/// on the web, assets are fetched relative to the page.
+#[cfg(target_arch = "wasm32")]
+pub fn shipped_assets_dir() -> PathBuf {
+    PathBuf::from("assets")
+}
+
+/// path to a file in the shipped assets directory, if it exists.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn shipped_asset(file: &str) -> Option<PathBuf> {
+    let path = shipped_assets_dir().join(file);
+    path.exists().then_some(path)
+}
+
+/// the served assets can't be checked without fetching them, so everything is assumed to ship.
+#[cfg(target_arch = "wasm32")]
+pub fn shipped_asset(file: &str) -> Option<PathBuf> {
+    Some(shipped_assets_dir().join(file))
+}
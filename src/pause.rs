@@ -3,10 +3,15 @@ use std::time::Duration;
 use bevy::prelude::*;
 use bevy::window::{CursorGrabMode, PrimaryWindow};
 
-use crate::gamestate::GameState;
+use crate::gamestate::InGameState;
+use crate::input::PlayerInput;
+use crate::quit::QuitRequested;
 
 fn cursor_grab(mut q_windows: Query<&mut Window, With<PrimaryWindow>>) {
-    let mut primary_window = q_windows.single_mut();
+    // no window when running headless
+    let Ok(mut primary_window) = q_windows.get_single_mut() else {
+        return;
+    };
 
     // for a game that doesn't use the cursor (like a shooter):
     // use `Locked` mode to keep the cursor in one place
@@ -17,28 +22,49 @@ fn cursor_grab(mut q_windows: Query<&mut Window, With<PrimaryWindow>>) {
 }
 
 fn cursor_ungrab(mut q_windows: Query<&mut Window, With<PrimaryWindow>>) {
-    let mut primary_window = q_windows.single_mut();
+    let Ok(mut primary_window) = q_windows.get_single_mut() else {
+        return;
+    };
 
     primary_window.cursor_options.grab_mode = CursorGrabMode::None;
     primary_window.cursor_options.visible = true;
 }
 
+fn freeze_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+fn unfreeze_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+/// drops held buttons, otherwise a release that happens while paused is never seen
+fn reset_player_input(mut player_input: ResMut<PlayerInput>) {
+    *player_input = PlayerInput::default();
+}
+
+// debounce runs on real time, since virtual time is frozen while paused
 #[derive(Resource, DerefMut, Deref)]
 struct PauseDebounceTimer(Timer);
 
 fn pause_menu_system(
     keyboard_input: Res<ButtonInput<KeyCode>>,
     gamepads: Query<(&Name, &Gamepad)>,
-    time: Res<Time>,
+    time: Res<Time<Real>>,
     mut pause_debounce_timer: ResMut<PauseDebounceTimer>,
-    mut next_state: ResMut<NextState<GameState>>,
+    mut next_state: ResMut<NextState<InGameState>>,
+    mut quits: EventWriter<QuitRequested>,
 ) {
+    if keyboard_input.just_pressed(KeyCode::KeyQ) {
+        quits.send(QuitRequested);
+    }
+
     let esc_pressed = keyboard_input.just_pressed(KeyCode::Escape);
     let start_pressed = gamepads
         .iter()
         .any(|(_, gamepad)| gamepad.just_pressed(GamepadButton::Start));
     if pause_debounce_timer.tick(time.delta()).finished() && (esc_pressed || start_pressed) {
-        next_state.set(GameState::InGame);
+        next_state.set(InGameState::Active);
         pause_debounce_timer.reset();
     }
 }
@@ -46,16 +72,16 @@ fn pause_menu_system(
 fn pause_input_handler(
     keyboard_input: Res<ButtonInput<KeyCode>>,
     gamepads: Query<(&Name, &Gamepad)>,
-    time: Res<Time>,
+    time: Res<Time<Real>>,
     mut pause_debounce_timer: ResMut<PauseDebounceTimer>,
-    mut next_state: ResMut<NextState<GameState>>,
+    mut next_state: ResMut<NextState<InGameState>>,
 ) {
     let esc_pressed = keyboard_input.just_pressed(KeyCode::Escape);
     let start_pressed = gamepads
         .iter()
         .any(|(_, gamepad)| gamepad.just_pressed(GamepadButton::Start));
     if pause_debounce_timer.tick(time.delta()).finished() && (esc_pressed || start_pressed) {
-        next_state.set(GameState::Paused);
+        next_state.set(InGameState::Paused);
         pause_debounce_timer.reset();
     }
 }
@@ -64,24 +90,23 @@ pub struct PausePlugin;
 
 impl Plugin for PausePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (
-                pause_input_handler.run_if(in_state(GameState::InGame)),
-                pause_menu_system.run_if(in_state(GameState::Paused)),
-            ),
-        )
-        .add_systems(
-            OnEnter(GameState::Paused),
-            cursor_ungrab.run_if(in_state(GameState::Paused)),
-        )
-        .add_systems(
-            OnEnter(GameState::InGame),
-            cursor_grab.run_if(in_state(GameState::InGame)),
-        )
-        .insert_resource(PauseDebounceTimer(Timer::new(
-            Duration::from_millis(200),
-            TimerMode::Once,
-        )));
+        app.add_sub_state::<InGameState>()
+            .add_systems(
+                Update,
+                (
+                    pause_input_handler.run_if(in_state(InGameState::Active)),
+                    pause_menu_system.run_if(in_state(InGameState::Paused)),
+                ),
+            )
+            .add_systems(
+                OnEnter(InGameState::Paused),
+                (cursor_ungrab, freeze_time, reset_player_input),
+            )
+            .add_systems(OnExit(InGameState::Paused), unfreeze_time)
+            .add_systems(OnEnter(InGameState::Active), cursor_grab)
+            .insert_resource(PauseDebounceTimer(Timer::new(
+                Duration::from_millis(200),
+                TimerMode::Once,
+            )));
     }
 }
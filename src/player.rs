@@ -1,19 +1,68 @@
 use std::f32::consts::FRAC_PI_2;
 
 /// unifies mouse input and gamepad input
-use bevy::prelude::*;
+use bevy::{
+    core_pipeline::{bloom::Bloom, tonemapping::Tonemapping},
+    prelude::*,
+};
 
-use crate::{input::PlayerInput, settings::GameSettings};
+use crate::{
+    bvh::SceneBvh,
+    gamestate::{GameState, InGameState},
+    gun::LidarGun,
+    input::PlayerInput,
+    scanner::{Loadout, ScannerHandles, ScannerProfile},
+    settings::{GameSettings, UserSettings},
+};
 
 #[derive(Component)]
 pub struct Player;
 
 const PITCH_LIMIT: f32 = FRAC_PI_2 - 0.05;
+/// how close the player gets to the front of `LidarInteractable` surfaces.
+/// only surfaces on the line of movement from the player's center are checked, so the player can still clip edges and
+/// thin geometry passing within this radius to the side.
+pub const PLAYER_RADIUS: f32 = 0.3;
+
+/// how much of `movement` can be made before getting within `PLAYER_RADIUS` of the first surface it heads into,
+/// measured along the surface normal so grazing approaches stop as far out as head on ones.
+/// returns that movement, and the normal of the surface if it got in the way.
+fn stop_short(bvh: &SceneBvh, position: Vec3, movement: Vec3) -> (Vec3, Option<Vec3>) {
+    let length = movement.length();
+    let Ok(direction) = Dir3::new(movement) else {
+        return (movement, None);
+    };
+    // no range limit, at a grazing angle the surface is hit far ahead of the player while already close to it
+    let Some(hit) = bvh.cast_ray(Ray3d::new(position, direction), f32::INFINITY) else {
+        return (movement, None);
+    };
+    // front faces face the ray, so this is positive
+    let cos = (-direction.dot(hit.normal)).max(f32::EPSILON);
+    let travel = hit.distance - PLAYER_RADIUS / cos;
+    if travel >= length {
+        return (movement, None);
+    }
+    (direction * travel.max(0.0), Some(hit.normal))
+}
+
+/// keeps the player `PLAYER_RADIUS` away from surfaces in the way, sliding whatever movement is left along the
+/// surface unless that runs into something else too.
+/// surfaces are one sided like they are for the lidar, so a player inside the level can always get out.
+fn collide(bvh: &SceneBvh, position: Vec3, movement: Vec3) -> Vec3 {
+    let (allowed, Some(normal)) = stop_short(bvh, position, movement) else {
+        return movement;
+    };
+    let remaining = movement - allowed;
+    let slide = remaining - normal * remaining.dot(normal);
+    let (slid, _) = stop_short(bvh, position + allowed, slide);
+    allowed + slid
+}
 
 pub fn player_movement_system(
     mut query: Query<&mut Transform, With<Player>>,
     player_input: Res<PlayerInput>,
     settings: Res<GameSettings>,
+    bvh: Res<SceneBvh>,
     time: Res<Time>,
 ) {
     if let Ok(mut transform) = query.get_single_mut() {
@@ -30,11 +79,71 @@ pub fn player_movement_system(
         let x_vec3 = -transform.local_z().as_vec3().cross(Vec3::Y).normalize();
         let z_vec3 = transform.local_x().as_vec3().cross(Vec3::Y).normalize();
 
-        transform.translation += settings.movement_speed_factor
+        let movement = settings.movement_speed_factor
             * delta_secs
             * (x_vec3 * player_input.movement_direction.y
-                + z_vec3 * player_input.movement_direction.x);
-        transform.translation.y +=
-            player_input.elevation * settings.movement_speed_factor * delta_secs;
+                + z_vec3 * player_input.movement_direction.x
+                + Vec3::Y * player_input.elevation);
+        transform.translation += collide(&bvh, transform.translation, movement);
+    }
+}
+
+fn setup_player(
+    mut commands: Commands,
+    user_settings: Res<UserSettings>,
+    game_settings: Res<GameSettings>,
+    scanners: Res<ScannerHandles>,
+    profiles: Res<Assets<ScannerProfile>>,
+) {
+    // start with the first scanner, or a default gun if the profiles failed to load
+    let gun = match scanners.0.first().and_then(|handle| profiles.get(handle)) {
+        Some(profile) => LidarGun::from_profile(profile, 0),
+        None => LidarGun::new(
+            0.4,
+            game_settings.gun_fire_rate,
+            game_settings.gun_max_range,
+        ),
+    };
+    commands
+        .spawn((
+            Player,
+            Transform::from_xyz(0.0, 0.0, 0.0),
+            Visibility::Visible,
+        ))
+        .insert((
+            gun,
+            Loadout {
+                scanners: scanners.0.clone(),
+            },
+        ))
+        .with_children(|e| {
+            e.spawn((
+                Camera {
+                    hdr: true,
+                    ..default()
+                },
+                Camera3d { ..default() },
+                Projection::Perspective(PerspectiveProjection {
+                    fov: user_settings.fov.clamp(45.0, 110.0).to_radians(),
+                    ..default()
+                }),
+                Tonemapping::TonyMcMapface,
+                Transform::from_xyz(0.0, 0.0, 0.0).looking_at(Vec3::X, Vec3::Y),
+                Bloom::NATURAL,
+            ));
+        });
+}
+
+/// spawns the player and their camera when the game starts, and moves them around
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        // pausing happens within InGame, so this only runs when coming from the main menu or a quickstart
+        app.add_systems(OnEnter(GameState::InGame), setup_player)
+            .add_systems(
+                Update,
+                player_movement_system.run_if(in_state(InGameState::Active)),
+            );
     }
 }
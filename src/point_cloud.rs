@@ -0,0 +1,235 @@
+use bevy::{
+    pbr::NotShadowCaster,
+    prelude::*,
+    render::{
+        mesh::{Indices, MeshVertexAttribute, PrimitiveTopology, VertexAttributeValues},
+        primitives::Aabb,
+        render_asset::RenderAssetUsages,
+        render_resource::VertexFormat,
+        view::VisibilitySystems,
+    },
+    utils::{HashMap, HashSet},
+};
+
+use crate::space::{LidarPoint, SphereHandles};
+
+/// per vertex copy of `LidarPoint::shader_data`
+pub const ATTRIBUTE_POINT_DATA: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_PointData", 988_540_917, VertexFormat::Float32x4);
+/// offset of the vertex from its point, before scaling by the point size.
+/// the vertex position is the point itself, so the shader can size and billboard points.
+pub const ATTRIBUTE_POINT_OFFSET: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_PointOffset", 988_540_918, VertexFormat::Float32x3);
+
+/// side length of the cubes points are batched into
+const CHUNK_SIZE: f32 = 8.0;
+
+/// a batch of points drawn as a single mesh
+#[derive(Default)]
+pub struct PointChunk {
+    /// entity with the chunk's mesh, spawned when the chunk is first rebuilt
+    pub entity: Option<Entity>,
+    pub points: Vec<Entity>,
+    pub dirty: bool,
+}
+
+/// points are grouped into a grid of chunks so that scanning only rebuilds the meshes that changed,
+/// instead of spawning a mesh per point.
+#[derive(Resource, Default)]
+pub struct PointChunks {
+    pub chunks: HashMap<IVec3, PointChunk>,
+    membership: HashMap<Entity, IVec3>,
+}
+
+impl PointChunks {
+    pub fn key(position: Vec3) -> IVec3 {
+        (position / CHUNK_SIZE).floor().as_ivec3()
+    }
+}
+
+pub fn assign_new_points(
+    mut chunks: ResMut<PointChunks>,
+    new_points: Query<(Entity, Ref<LidarPoint>), Changed<LidarPoint>>,
+    mut removed_points: RemovedComponents<LidarPoint>,
+) {
+    let removed: HashSet<Entity> = removed_points.read().collect();
+    if !removed.is_empty() {
+        let chunks = &mut *chunks;
+        for entity in &removed {
+            let Some(key) = chunks.membership.remove(entity) else {
+                continue;
+            };
+            if let Some(chunk) = chunks.chunks.get_mut(&key) {
+                chunk.dirty = true;
+            }
+        }
+        for chunk in chunks.chunks.values_mut().filter(|chunk| chunk.dirty) {
+            chunk.points.retain(|entity| !removed.contains(entity));
+        }
+    }
+
+    for (entity, point) in &new_points {
+        if !point.is_added() {
+            // refreshed in place by a voxel merge, see `PointStorage::merge`.
+            // known debt: this rebuilds the whole chunk mesh for one point, rather than patching its vertices.
+            if let Some(chunk) = chunks
+                .membership
+                .get(&entity)
+                .copied()
+                .and_then(|key| chunks.chunks.get_mut(&key))
+            {
+                chunk.dirty = true;
+            }
+            continue;
+        }
+        let key = PointChunks::key(point.position);
+        let chunk = chunks.chunks.entry(key).or_default();
+        chunk.points.push(entity);
+        chunk.dirty = true;
+        chunks.membership.insert(entity, key);
+    }
+}
+
+pub fn rebuild_dirty_chunks(
+    mut commands: Commands,
+    mut chunks: ResMut<PointChunks>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    handles: Res<SphereHandles>,
+    points: Query<&LidarPoint>,
+) {
+    let (Some(template), Some(material)) = (
+        handles.mesh.as_ref().and_then(|handle| meshes.get(handle)),
+        handles.material.clone(),
+    ) else {
+        return;
+    };
+    let template = PointTemplate::new(template);
+    if handles.is_changed() {
+        // the point shape changed
+        for chunk in chunks.chunks.values_mut() {
+            chunk.dirty = true;
+        }
+    }
+
+    let mut emptied = Vec::new();
+    for (key, chunk) in chunks.chunks.iter_mut().filter(|(_, chunk)| chunk.dirty) {
+        chunk.dirty = false;
+        if chunk.points.is_empty() {
+            emptied.push(*key);
+            continue;
+        }
+
+        let chunk_points: Vec<LidarPoint> = points.iter_many(&chunk.points).copied().collect();
+        let mesh = meshes.add(template.build(&chunk_points));
+        match chunk.entity {
+            Some(entity) => {
+                // the aabb isn't recomputed when the mesh changes, removing it makes bevy calculate it again
+                commands
+                    .entity(entity)
+                    .insert(Mesh3d(mesh))
+                    .remove::<Aabb>();
+            }
+            None => {
+                chunk.entity = Some(
+                    commands
+                        .spawn((
+                            Mesh3d(mesh),
+                            MeshMaterial3d(material.clone()),
+                            Transform::IDENTITY,
+                            Visibility::Visible,
+                            NotShadowCaster,
+                        ))
+                        .id(),
+                );
+            }
+        }
+    }
+
+    for key in emptied {
+        if let Some(entity) = chunks.chunks.remove(&key).and_then(|chunk| chunk.entity) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// vertices and indices of the shape each point is drawn with
+pub(crate) struct PointTemplate {
+    positions: Vec<Vec3>,
+    indices: Vec<u32>,
+    /// whether the shader scales the shape by the point size, see `ATTRIBUTE_POINT_OFFSET`
+    sized: bool,
+}
+
+impl PointTemplate {
+    pub(crate) fn new(mesh: &Mesh) -> Self {
+        Self::from_mesh(mesh, true)
+    }
+
+    /// a shape drawn as is, ignoring the point size and shape settings
+    pub(crate) fn fixed(mesh: &Mesh) -> Self {
+        Self::from_mesh(mesh, false)
+    }
+
+    fn from_mesh(mesh: &Mesh, sized: bool) -> Self {
+        let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float32x3(positions)) => {
+                positions.iter().map(|p| Vec3::from_array(*p)).collect()
+            }
+            _ => vec![],
+        };
+        let indices = mesh
+            .indices()
+            .map(|indices| indices.iter().map(|i| i as u32).collect())
+            .unwrap_or_else(|| (0..positions.len() as u32).collect());
+        Self {
+            positions,
+            indices,
+            sized,
+        }
+    }
+
+    pub(crate) fn build(&self, points: &[LidarPoint]) -> Mesh {
+        let mut positions = Vec::with_capacity(points.len() * self.positions.len());
+        let mut offsets = Vec::with_capacity(points.len() * self.positions.len());
+        let mut point_data = Vec::with_capacity(points.len() * self.positions.len());
+        let mut indices = Vec::with_capacity(points.len() * self.indices.len());
+        for point in points {
+            let base = positions.len() as u32;
+            for vertex in &self.positions {
+                if self.sized {
+                    positions.push(point.position.to_array());
+                    offsets.push(vertex.to_array());
+                } else {
+                    positions.push((*vertex + point.position).to_array());
+                    offsets.push([0.0; 3]);
+                }
+            }
+            point_data.extend(std::iter::repeat(point.shader_data()).take(self.positions.len()));
+            indices.extend(self.indices.iter().map(|i| base + i));
+        }
+
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(ATTRIBUTE_POINT_DATA, point_data)
+        .with_inserted_attribute(ATTRIBUTE_POINT_OFFSET, offsets)
+        .with_inserted_indices(Indices::U32(indices))
+    }
+}
+
+/// draws every point, in chunks. used with storage backends that aren't a `SpatialPointStorage`, see `lod::LodPlugin` for those.
+pub struct PointCloudPlugin;
+
+impl Plugin for PointCloudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PointChunks>()
+            .add_systems(
+                PostUpdate,
+                (assign_new_points, rebuild_dirty_chunks)
+                    .chain()
+                    .before(VisibilitySystems::CalculateBounds),
+            );
+    }
+}
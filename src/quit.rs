@@ -0,0 +1,191 @@
+use std::marker::PhantomData;
+
+use bevy::{prelude::*, window::WindowCloseRequested};
+
+use crate::{
+    assets::{write_settings, SettingsLoaded, USER_CONFIG_FILE},
+    gamestate::GameState,
+    gun::LidarGun,
+    level::CurrentLevel,
+    paths::GamePaths,
+    player::Player,
+    save::{PendingSaves, SessionSave, UnsavedScan, QUICKSAVE_FILE},
+    settings::UserSettings,
+    space::{LidarInteractable, MissLog, PointStorage, Space},
+};
+
+/// asks the game to quit. goes through a confirmation prompt first if there's unsaved scan data.
+#[derive(Event, Default, Clone, Copy, Debug)]
+pub struct QuitRequested;
+
+/// decisions made before entering `GameState::Quitting`
+#[derive(Resource, Default)]
+pub struct QuitOptions {
+    /// quicksave the session before shutting down
+    pub save_first: bool,
+}
+
+#[derive(Component)]
+struct QuitPrompt;
+
+fn window_close_requested(
+    mut closes: EventReader<WindowCloseRequested>,
+    mut quits: EventWriter<QuitRequested>,
+) {
+    if closes.read().count() > 0 {
+        quits.send(QuitRequested);
+    }
+}
+
+fn handle_quit_request(
+    mut commands: Commands,
+    mut requests: EventReader<QuitRequested>,
+    unsaved: Res<UnsavedScan>,
+    prompt: Query<(), With<QuitPrompt>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if requests.read().count() == 0 || !prompt.is_empty() {
+        return;
+    }
+    if !unsaved.0 {
+        next_state.set(GameState::Quitting);
+        return;
+    }
+
+    commands.spawn((
+        QuitPrompt,
+        Text::new("unsaved scan data\n[Y] save and quit  [N] quit without saving  [C] cancel"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(40.0),
+            left: Val::Percent(30.0),
+            padding: UiRect::all(Val::Px(12.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+    ));
+}
+
+fn quit_prompt_input(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    prompt: Query<Entity, With<QuitPrompt>>,
+    mut options: ResMut<QuitOptions>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Ok(entity) = prompt.get_single() else {
+        return;
+    };
+    if keyboard.just_pressed(KeyCode::KeyY) {
+        options.save_first = true;
+        next_state.set(GameState::Quitting);
+    } else if keyboard.just_pressed(KeyCode::KeyN) {
+        next_state.set(GameState::Quitting);
+    } else if keyboard.just_pressed(KeyCode::KeyC) {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// saves if requested, waits for any in flight saves, and despawns the session
+pub fn shutdown_session<S: PointStorage + Send + Sync + 'static>(
+    mut commands: Commands,
+    mut space: ResMut<Space<S>>,
+    misses: Res<MissLog>,
+    mut pending: ResMut<PendingSaves>,
+    options: Res<QuitOptions>,
+    paths: Res<GamePaths>,
+    time: Res<Time>,
+    level: Option<Res<CurrentLevel>>,
+    player: Query<(Entity, &Transform, &LidarGun), With<Player>>,
+    level_entities: Query<Entity, With<LidarInteractable>>,
+) {
+    if options.save_first {
+        match (player.get_single(), level) {
+            (Ok((_, transform, gun)), Some(level)) => {
+                let path = paths.scans_dir().join(QUICKSAVE_FILE);
+                let save = SessionSave::capture(
+                    &space,
+                    &misses,
+                    &level,
+                    transform,
+                    gun,
+                    time.elapsed_secs(),
+                );
+                match save.write(&path) {
+                    Ok(()) => info!("saved session to {:?}", path),
+                    Err(e) => error!("failed to save session - {}", e),
+                }
+            }
+            _ => warn!("no session to save"),
+        }
+    }
+    pending.flush();
+
+    for entity in space
+        .clear()
+        .into_iter()
+        .chain(player.iter().map(|(entity, ..)| entity))
+        .chain(level_entities.iter())
+    {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn write_user_settings(paths: Res<GamePaths>, settings: Res<UserSettings>) {
+    let path = paths.config_dir.join(USER_CONFIG_FILE);
+    if let Err(e) = write_settings(&path, &*settings) {
+        error!("failed to write user settings to {:?} - {}", path, e);
+    }
+}
+
+fn exit_app(mut exit: EventWriter<AppExit>) {
+    exit.send(AppExit::Success);
+}
+
+/// handles quit requests and the `Quitting` state, shutting down the session kept in `Space<S>`
+pub struct QuitPlugin<S> {
+    _storage: PhantomData<fn() -> S>,
+}
+
+impl<S> Default for QuitPlugin<S> {
+    fn default() -> Self {
+        Self {
+            _storage: PhantomData,
+        }
+    }
+}
+
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum QuitSet {
+    Shutdown,
+    Exit,
+}
+
+impl<S: PointStorage + Send + Sync + 'static> Plugin for QuitPlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.add_event::<QuitRequested>()
+            .insert_resource(QuitOptions::default())
+            .configure_sets(
+                OnEnter(GameState::Quitting),
+                (QuitSet::Shutdown, QuitSet::Exit).chain(),
+            )
+            .add_systems(
+                Update,
+                (window_close_requested, handle_quit_request, quit_prompt_input).chain(),
+            )
+            .add_systems(
+                OnEnter(GameState::Quitting),
+                shutdown_session::<S>.in_set(QuitSet::Shutdown),
+            )
+            .add_systems(
+                OnEnter(GameState::Quitting),
+                (
+                    // quitting while still loading leaves the player's user.ron as it was
+                    write_user_settings.run_if(resource_exists::<SettingsLoaded>),
+                    exit_app,
+                )
+                    .chain()
+                    .in_set(QuitSet::Exit),
+            );
+    }
+}
@@ -0,0 +1,272 @@
+use std::{
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    prelude::*,
+    scene::ron::{self, ser::PrettyConfig, Serializer},
+    tasks::{block_on, futures_lite::future, IoTaskPool, Task},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cli::Cli,
+    gun::LidarGun,
+    level::CurrentLevel,
+    paths::GamePaths,
+    player::Player,
+    space::{spawn_point, LidarMiss, LidarPoint, MissLog, PendingShots, PointStorage, Space},
+    util::LidarRng,
+};
+
+/// bump this whenever `SessionSave` changes in a way old saves can't be read with
+const SAVE_VERSION: u32 = 4;
+pub const QUICKSAVE_FILE: &'static str = "quicksave.ron";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlayerSave {
+    pub translation: Vec3,
+    pub rotation: Quat,
+}
+
+/// everything needed to resume a partially scanned level
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SessionSave {
+    pub version: u32,
+    pub level: String,
+    pub seed: u64,
+    /// `Time<Virtual>` elapsed seconds at the time of saving, point timestamps are relative to this
+    pub time: f32,
+    pub player: PlayerSave,
+    pub gun: LidarGun,
+    /// oldest first, so that trimming behaves the same after loading
+    pub points: Vec<LidarPoint>,
+    /// oldest first, like `points`
+    pub misses: Vec<LidarMiss>,
+}
+
+impl SessionSave {
+    pub fn capture<S: PointStorage>(
+        space: &Space<S>,
+        misses: &MissLog,
+        level: &CurrentLevel,
+        transform: &Transform,
+        gun: &LidarGun,
+        time: f32,
+    ) -> Self {
+        SessionSave {
+            version: SAVE_VERSION,
+            level: level.name.clone(),
+            seed: level.seed,
+            time,
+            player: PlayerSave {
+                translation: transform.translation,
+                rotation: transform.rotation,
+            },
+            gun: gun.clone(),
+            points: space.accelerator.points(),
+            misses: misses.0.iter().copied().collect(),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let file = File::create(path)?;
+        // depth limit keeps each point on a single line
+        let mut serializer = Serializer::new(file, Some(PrettyConfig::new().depth_limit(2)))
+            .map_err(io::Error::other)?;
+        self.serialize(&mut serializer).map_err(io::Error::other)
+    }
+
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let save: SessionSave = ron::de::from_reader(file).map_err(io::Error::other)?;
+        if save.version != SAVE_VERSION {
+            return Err(io::Error::other(format!(
+                "save version {} is not supported, expected {}",
+                save.version, SAVE_VERSION
+            )));
+        }
+        Ok(save)
+    }
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct SaveSession {
+    pub path: PathBuf,
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct LoadSession {
+    pub path: PathBuf,
+}
+
+/// whether points have been scanned since the session was last saved or loaded
+#[derive(Resource, Default)]
+pub struct UnsavedScan(pub bool);
+
+/// saves that are still being written to disk
+#[derive(Resource, Default)]
+pub struct PendingSaves(pub Vec<Task<io::Result<PathBuf>>>);
+
+fn report_save(result: io::Result<PathBuf>) {
+    match result {
+        Ok(path) => info!("saved session to {:?}", path),
+        Err(e) => error!("failed to save session - {}", e),
+    }
+}
+
+impl PendingSaves {
+    /// blocks until every pending save has been written
+    pub fn flush(&mut self) {
+        for task in self.0.drain(..) {
+            report_save(block_on(task));
+        }
+    }
+}
+
+pub fn session_hotkeys(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    paths: Res<GamePaths>,
+    mut saves: EventWriter<SaveSession>,
+    mut loads: EventWriter<LoadSession>,
+) {
+    let quicksave = paths.scans_dir().join(QUICKSAVE_FILE);
+    if keyboard.just_pressed(KeyCode::F5) {
+        saves.send(SaveSession {
+            path: quicksave.clone(),
+        });
+    }
+    if keyboard.just_pressed(KeyCode::F9) {
+        loads.send(LoadSession { path: quicksave });
+    }
+}
+
+/// resumes the session passed with `--load`, if any
+pub fn load_session_from_cli(cli: Res<Cli>, mut loads: EventWriter<LoadSession>) {
+    if let Some(path) = &cli.load {
+        loads.send(LoadSession { path: path.clone() });
+    }
+}
+
+pub fn save_session<S: PointStorage + Send + Sync + 'static>(
+    mut requests: EventReader<SaveSession>,
+    mut pending: ResMut<PendingSaves>,
+    mut unsaved: ResMut<UnsavedScan>,
+    space: Res<Space<S>>,
+    misses: Res<MissLog>,
+    level: Res<CurrentLevel>,
+    time: Res<Time>,
+    player: Query<(&Transform, &LidarGun), With<Player>>,
+) {
+    for request in requests.read() {
+        let Ok((transform, gun)) = player.get_single() else {
+            warn!("no player to save");
+            continue;
+        };
+        let save = SessionSave::capture(
+            &space,
+            &misses,
+            &level,
+            transform,
+            gun,
+            time.elapsed_secs(),
+        );
+        unsaved.0 = false;
+        let path = request.path.clone();
+        let task = IoTaskPool::get().spawn(async move { save.write(&path).map(|_| path) });
+        pending.0.push(task);
+    }
+}
+
+pub fn poll_pending_saves(mut pending: ResMut<PendingSaves>) {
+    pending
+        .0
+        .retain_mut(|task| match block_on(future::poll_once(task)) {
+            Some(result) => {
+                report_save(result);
+                false
+            }
+            None => true,
+        });
+}
+
+pub fn load_session<S: PointStorage + Send + Sync + 'static>(
+    mut commands: Commands,
+    mut requests: EventReader<LoadSession>,
+    mut space: ResMut<Space<S>>,
+    mut misses: ResMut<MissLog>,
+    mut pending: ResMut<PendingShots>,
+    mut level: ResMut<CurrentLevel>,
+    mut rng: ResMut<LidarRng>,
+    mut unsaved: ResMut<UnsavedScan>,
+    time: Res<Time>,
+    mut player: Query<(&mut Transform, &mut LidarGun), With<Player>>,
+) {
+    // only the most recent request matters
+    let Some(request) = requests.read().last() else {
+        return;
+    };
+    let save = match SessionSave::read(&request.path) {
+        Ok(save) => save,
+        Err(e) => {
+            error!("failed to load session from {:?} - {}", request.path, e);
+            return;
+        }
+    };
+
+    if save.level != level.name {
+        // TODO: rebuild the scene once there's more than one level
+        warn!(
+            "save is from level {}, restoring its points into {}",
+            save.level, level.name
+        );
+    }
+    level.seed = save.seed;
+    *rng = LidarRng::seeded(save.seed);
+
+    if let Ok((mut transform, mut gun)) = player.get_single_mut() {
+        transform.translation = save.player.translation;
+        transform.rotation = save.player.rotation;
+        *gun = save.gun;
+    }
+
+    for entity in space.clear() {
+        commands.entity(entity).despawn_recursive();
+    }
+    // shots fired before the load would otherwise land in the loaded scan
+    pending.0.clear();
+    // keep point ages the same as when they were saved
+    let time_offset = time.elapsed_secs() - save.time;
+    let points: Vec<LidarPoint> = save
+        .points
+        .iter()
+        .map(|point| LidarPoint {
+            timestamp: point.timestamp + time_offset,
+            ..*point
+        })
+        .collect();
+    let entities: Vec<Entity> = points
+        .iter()
+        .map(|point| spawn_point(&mut commands, point))
+        .collect();
+    space.add_points(&points, &entities);
+    for entity in space.trim() {
+        commands.entity(entity).despawn_recursive();
+    }
+    misses.0 = save
+        .misses
+        .iter()
+        .map(|miss| LidarMiss {
+            timestamp: miss.timestamp + time_offset,
+            ..*miss
+        })
+        .collect();
+    unsaved.0 = false;
+    info!(
+        "loaded session from {:?} with {} points",
+        request.path,
+        points.len()
+    );
+}
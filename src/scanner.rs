@@ -0,0 +1,143 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gun::LidarGun,
+    input::PlayerInput,
+    material::{CustomMaterial, MAX_POINT_SOURCES},
+    space::SphereHandles,
+    util::solid_angle_sample,
+};
+
+/// scanner profiles the player can switch between, in number key order
+pub const SCANNER_PROFILES: &[&'static str] = &[
+    "scanners/cone.scanner",
+    "scanners/rangefinder.scanner",
+    "scanners/line.scanner",
+    "scanners/spinner.scanner",
+];
+pub const SCANNER_FILE_EXTENSION: &[&'static str] = &["scanner"];
+
+/// how a scanner spreads its shots
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ScanPattern {
+    /// randomly within a cone around the aim direction
+    #[default]
+    Cone,
+    /// straight ahead
+    Rangefinder,
+    /// randomly along a horizontal line through the aim direction
+    Line,
+    /// all around the player, within a band around the horizon. ignores aiming.
+    Spinner,
+}
+
+impl ScanPattern {
+    /// direction of a single shot. `spread` is the cone radius, half the line width, or half the spinner's vertical coverage.
+    pub fn sample(&self, rng: &mut impl Rng, spread: f32, transform: &Transform) -> Vec3 {
+        // samples are centered around Vec3::Z, with the player aiming along local x
+        let local = match self {
+            ScanPattern::Cone => solid_angle_sample(rng, spread),
+            ScanPattern::Rangefinder => Vec3::Z,
+            ScanPattern::Line => {
+                let angle = rng.random_range(-1.0f32..=1.0) * spread;
+                Vec3::new(0.0, angle.sin(), angle.cos())
+            }
+            ScanPattern::Spinner => {
+                let azimuth = rng.random::<f32>() * TAU;
+                let elevation = rng.random_range(-1.0f32..=1.0) * spread;
+                return Vec3::new(
+                    elevation.cos() * azimuth.cos(),
+                    elevation.sin(),
+                    elevation.cos() * azimuth.sin(),
+                );
+            }
+        };
+        transform.compute_matrix().transform_vector3(local.zxy())
+    }
+}
+
+/// a scanner type, loaded from a `.scanner` file
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, Debug)]
+pub struct ScannerProfile {
+    pub name: String,
+    pub pattern: ScanPattern,
+    /// initial spread in radians, see `ScanPattern::sample`
+    pub spread: f32,
+    pub fire_rate: f32,
+    pub max_range: f32,
+    /// color of this scanner's points
+    pub color: LinearRgba,
+}
+
+#[derive(Resource, Default)]
+pub struct ScannerHandles(pub Vec<Handle<ScannerProfile>>);
+
+/// the scanners the player carries. the active one is the one at `LidarGun::source`.
+#[derive(Component, Clone, Debug)]
+pub struct Loadout {
+    pub scanners: Vec<Handle<ScannerProfile>>,
+}
+
+pub fn switch_scanner(
+    player_input: Res<PlayerInput>,
+    profiles: Res<Assets<ScannerProfile>>,
+    mut query: Query<(&Loadout, &mut LidarGun)>,
+) {
+    let Ok((loadout, mut gun)) = query.get_single_mut() else {
+        return;
+    };
+    let count = loadout.scanners.len();
+    if count == 0 {
+        return;
+    }
+    let current = gun.source as usize;
+    let next = match (player_input.scanner_select, player_input.scanner_cycle) {
+        (Some(index), _) if index < count => index,
+        (_, cycle) if cycle != 0 => (current as i32 + cycle).rem_euclid(count as i32) as usize,
+        _ => return,
+    };
+    if next == current {
+        return;
+    }
+
+    let Some(profile) = profiles.get(&loadout.scanners[next]) else {
+        warn!("scanner {} isn't loaded", next);
+        return;
+    };
+    // scanners share one heat sink, so switching doesn't cool the gun down
+    *gun = LidarGun {
+        heat: gun.heat,
+        overheated: gun.overheated,
+        ..LidarGun::from_profile(profile, next as u32)
+    };
+    info!("switched to {}", profile.name);
+}
+
+/// colors each scanner's points with its profile color
+pub fn sync_scanner_colors(
+    mut events: EventReader<AssetEvent<ScannerProfile>>,
+    scanners: Res<ScannerHandles>,
+    profiles: Res<Assets<ScannerProfile>>,
+    handles: Res<SphereHandles>,
+    mut materials: ResMut<Assets<CustomMaterial>>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    let Some(material) = handles
+        .material
+        .as_ref()
+        .and_then(|handle| materials.get_mut(handle))
+    else {
+        return;
+    };
+    for (i, handle) in scanners.0.iter().enumerate().take(MAX_POINT_SOURCES) {
+        if let Some(profile) = profiles.get(handle) {
+            material.source_colors[i] = profile.color;
+        }
+    }
+}
@@ -3,20 +3,299 @@ use bevy::{
     prelude::{Asset, Resource},
     reflect::TypePath,
 };
+use bevy_common_assets::ron::RonAssetPlugin;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    assets::{load_assets, loading_state_watcher, loading_update, AssetsTracking},
+    gamestate::GameState,
+    scanner::{ScannerHandles, ScannerProfile, SCANNER_FILE_EXTENSION},
+};
+
+pub const USERFILE_EXTENSION: &[&'static str] = &["ron"];
+pub const CONFIG_FILE_EXTENSION: &[&'static str] = &["rconfig"];
+
 #[derive(Asset, TypePath, Serialize, Deserialize, Resource, Default, Clone)]
 pub struct UserSettings {
     pub sensitivity: Vec2,
     pub fov: f32,
+    /// missing from older user files, so it falls back to the defaults
+    #[serde(default)]
+    pub points: PointStyle,
+    #[serde(default)]
+    pub tracers: TracerSettings,
+}
+
+/// beams drawn from the gun to where each shot landed
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TracerSettings {
+    pub enabled: bool,
+    /// seconds a tracer stays visible, fading out over that time
+    pub lifetime: f32,
+    /// brightness multiplier, above 1 blooms
+    pub intensity: f32,
+    /// length of tracers for shots that hit nothing
+    pub miss_length: f32,
+    /// oldest tracers are dropped beyond this
+    pub max_tracers: usize,
+}
+
+impl Default for TracerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            lifetime: 0.05,
+            intensity: 2.0,
+            miss_length: 100.0,
+            max_tracers: 4000,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PointShape {
+    #[default]
+    Sphere,
+    /// camera facing circle
+    Disc,
+    /// camera facing square
+    Quad,
+    /// camera facing soft circle, always `PointStyle::pixels` in size
+    Splat,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PointSizeMode {
+    /// `PointStyle::radius` meters, so points shrink on screen with distance
+    #[default]
+    World,
+    /// `PointStyle::pixels` on screen, divided by `1 + PointStyle::attenuation * distance`
+    Attenuated,
 }
 
+/// how points are drawn
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PointStyle {
+    pub shape: PointShape,
+    pub size_mode: PointSizeMode,
+    /// radius in meters, for `PointSizeMode::World`
+    pub radius: f32,
+    /// radius in pixels, for `PointSizeMode::Attenuated` and `PointShape::Splat`
+    pub pixels: f32,
+    pub attenuation: f32,
+}
+
+impl Default for PointStyle {
+    fn default() -> Self {
+        Self {
+            shape: PointShape::Sphere,
+            size_mode: PointSizeMode::World,
+            radius: 0.1,
+            pixels: 4.0,
+            attenuation: 0.05,
+        }
+    }
+}
+
+/// fields missing from the file take their defaults, so config overrides written for older versions keep loading
 #[derive(Asset, TypePath, Serialize, Deserialize, Resource, Default, Clone)]
+#[serde(default)]
 pub struct GameSettings {
     pub color_distance_factor: f32,
+    /// seconds for a point to fade out completely, 0 to never fade
+    pub point_fade_secs: f32,
+    /// how much glancing hits are dimmed. 0 for not at all, 1 for full cosine falloff
+    pub point_incidence_falloff: f32,
+    /// range up to which returns don't lose intensity with distance, 0 to disable distance falloff
+    pub intensity_reference_distance: f32,
+    /// only for the gun the player gets when no scanner profile loaded, profiles always set their own
+    #[serde(default = "default_gun_fire_rate")]
     pub gun_fire_rate: f32,
+    pub gun_heat: GunHeatSettings,
+    /// meters, anything further away is a miss. like `gun_fire_rate`, scanner profiles set their own.
+    #[serde(default = "default_gun_max_range")]
+    pub gun_max_range: f32,
+    /// number of misses kept for saves and analysis, 0 to not record misses
+    pub miss_log_limit: usize,
     pub max_shots_per_frame: u32,
+    /// shots beyond `max_shots_per_frame` wait for later frames, up to this many. the oldest are dropped past that.
+    pub max_pending_shots: usize,
+    /// milliseconds per frame to spend resolving shots, fewer than `max_shots_per_frame` are resolved when they
+    /// wouldn't fit. 0 to always resolve up to `max_shots_per_frame`.
+    pub shot_time_budget_ms: f32,
     pub max_gun_spread: f32,
     pub movement_speed_factor: f32,
     pub points_limit: Option<usize>,
+    /// side length of the voxels hits are merged in. a hit in a voxel that already has a point refreshes that point
+    /// instead of adding another, 0 to keep every hit.
+    pub voxel_size: f32,
+    /// level to load, defaults to `level::DEFAULT_LEVEL`
+    pub level: Option<String>,
+    /// seed for gameplay randomness, random if `None`
+    pub seed: Option<u64>,
+    /// chunk level of detail, used with the octree storage backend
+    pub lod: LodSettings,
+    pub hit_lights: HitLightSettings,
+    /// sensor noise applied to every shot
+    pub noise: NoiseSettings,
+}
+
+/// a fire rate of 0 would never fire
+fn default_gun_fire_rate() -> f32 {
+    1000.0
+}
+
+/// older files had no max range, a range of 0 would make every shot miss
+fn default_gun_max_range() -> f32 {
+    100.0
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum NoiseModelKind {
+    /// returns are exact
+    #[default]
+    None,
+    /// see `noise::GaussianNoise`
+    Gaussian,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct NoiseSettings {
+    pub model: NoiseModelKind,
+    /// standard deviation of the measured range, per meter of range
+    pub range_sigma: f32,
+    /// standard deviation of the shot direction, in radians
+    pub angular_sigma: f32,
+    /// chance for a hit to produce no return
+    pub dropout: f32,
+    /// hits at a larger incidence angle than this produce no return, in radians
+    pub max_incidence_angle: f32,
+}
+
+/// exact returns, with parameters that make sense as soon as a model is picked
+impl Default for NoiseSettings {
+    fn default() -> Self {
+        Self {
+            model: NoiseModelKind::None,
+            range_sigma: 0.001,
+            angular_sigma: 0.0005,
+            dropout: 0.02,
+            // ~85 degrees
+            max_incidence_angle: 1.48,
+        }
+    }
+}
+
+/// spin up and overheating of `gun::LidarGun`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GunHeatSettings {
+    /// seconds of firing to reach the full fire rate, and of not firing to spin back down
+    pub spin_up_secs: f32,
+    /// fraction of the full fire rate the gun starts at
+    pub min_rate_fraction: f32,
+    /// seconds of firing at the full rate until the gun overheats, 0 to never overheat
+    pub overheat_secs: f32,
+    /// seconds for a fully heated gun to cool down completely
+    pub cooldown_secs: f32,
+    /// an overheated gun can fire again once it cools down to this
+    pub recover_heat: f32,
+}
+
+impl Default for GunHeatSettings {
+    fn default() -> Self {
+        Self {
+            spin_up_secs: 1.0,
+            min_rate_fraction: 0.25,
+            overheat_secs: 6.0,
+            cooldown_secs: 3.0,
+            recover_heat: 0.25,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum HitLightMode {
+    #[default]
+    Off,
+    /// a light at each of the most recent hits
+    Recent,
+    /// a light at each of the densest clusters of recent hits
+    Clustered,
+}
+
+/// real lights cast by lidar hits onto the level
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HitLightSettings {
+    pub mode: HitLightMode,
+    /// capped at `hit_lights::MAX_HIT_LIGHTS`
+    pub max_lights: usize,
+    /// luminous power of a light, in lumens
+    pub intensity: f32,
+    pub range: f32,
+    /// side length of the cubes hits are clustered into
+    pub cluster_size: f32,
+    /// number of recent hits considered for clustering
+    pub cluster_window: usize,
+}
+
+impl Default for HitLightSettings {
+    fn default() -> Self {
+        Self {
+            mode: HitLightMode::Off,
+            max_lights: 32,
+            intensity: 100000.0,
+            range: 4.0,
+            cluster_size: 2.0,
+            cluster_window: 2000,
+        }
+    }
+}
+
+/// distances are measured from the camera to the center of an octree leaf
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LodSettings {
+    /// leaves closer than this draw every point
+    pub near_distance: f32,
+    /// leaves closer than this draw every `mid_stride`th point, leaves further away draw a single impostor cube
+    pub far_distance: f32,
+    pub mid_stride: usize,
+    /// points per cubic meter at which an impostor reaches full brightness
+    pub impostor_full_density: f32,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            near_distance: 24.0,
+            far_distance: 64.0,
+            mid_stride: 4,
+            impostor_full_density: 2.0,
+        }
+    }
+}
+
+/// loads the settings files and scanner profiles during `GameState::Loading`
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AssetsTracking::new())
+            .add_plugins(RonAssetPlugin::<UserSettings>::new(USERFILE_EXTENSION))
+            .add_plugins(RonAssetPlugin::<GameSettings>::new(CONFIG_FILE_EXTENSION))
+            .add_plugins(RonAssetPlugin::<ScannerProfile>::new(SCANNER_FILE_EXTENSION))
+            .insert_resource(ScannerHandles::default())
+            .insert_resource(UserSettings::default())
+            .insert_resource(GameSettings::default())
+            .add_systems(OnEnter(GameState::Loading), load_assets)
+            .add_systems(
+                Update,
+                (
+                    loading_update,
+                    loading_state_watcher::<UserSettings>,
+                    // loading_state_watcher::<Image>,
+                )
+                    .run_if(in_state(GameState::Loading)),
+            );
+    }
 }
@@ -1,34 +1,318 @@
 use std::collections::VecDeque;
 
-use bevy::{math::vec3, picking::backend::ray::RayMap, prelude::*};
+use bevy::{
+    diagnostic::Diagnostics,
+    math::vec3,
+    picking::backend::ray::RayMap,
+    prelude::*,
+    // std's Instant panics on wasm
+    utils::{HashMap, HashSet, Instant},
+};
+use serde::{Deserialize, Serialize};
 
-use crate::{gun::LidarShotFired, settings::GameSettings};
+use crate::{
+    bvh::{BvhPlugin, SceneBvh},
+    diagnostics,
+    gamestate::InGameState,
+    gun::{LidarShotFired, LidarShotResolved},
+    save::UnsavedScan,
+    settings::GameSettings,
+    noise::{sync_noise_model, LidarNoise},
+    util::LidarRng,
+};
 use crate::{material::CustomMaterial, player::Player, settings::UserSettings};
 
+/// a single lidar return, as stored in `Space` and on the point's entity
+#[derive(Component, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct LidarPoint {
+    pub position: Vec3,
+    /// surface normal at the hit, facing the gun
+    pub normal: Vec3,
+    /// distance from the gun to the hit
+    pub distance: f32,
+    /// angle between the incoming ray and the surface normal, in radians
+    pub incidence_angle: f32,
+    /// strength of the return, see `return_intensity`
+    pub intensity: f32,
+    /// `Time<Virtual>` elapsed seconds when the point was scanned
+    pub timestamp: f32,
+    /// which gun produced the point
+    pub source: u32,
+    /// number of returns merged into this point, see `refresh`. saves from before voxels had one per point.
+    #[serde(default = "single_return")]
+    pub returns: u32,
+}
+
+fn single_return() -> u32 {
+    1
+}
+
+impl LidarPoint {
+    /// folds a newer return from the same voxel into this point. it stays where it was first scanned,
+    /// its intensity becomes the mean over all of its returns and everything else is taken from the new return.
+    pub fn refresh(&mut self, newer: &LidarPoint) {
+        self.returns += newer.returns;
+        self.intensity +=
+            (newer.intensity - self.intensity) * newer.returns as f32 / self.returns as f32;
+        self.distance = newer.distance;
+        self.incidence_angle = newer.incidence_angle;
+        self.timestamp = newer.timestamp;
+        self.source = newer.source;
+    }
+
+    /// per vertex data for the point shader, see `point_cloud::ATTRIBUTE_POINT_DATA`
+    pub fn shader_data(&self) -> [f32; 4] {
+        [
+            self.distance,
+            self.intensity,
+            self.timestamp,
+            self.source as f32,
+        ]
+    }
+}
+
 pub trait PointStorage {
     /// method to add points
-    fn add_points(&mut self, points: &[Vec3], entities: &[Entity]);
+    fn add_points(&mut self, points: &[LidarPoint], entities: &[Entity]);
     /// method that returns entities that should be despawned to adhere to some given point limit.
     fn trim(&mut self) -> Vec<Entity>;
+    /// number of points currently stored
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// changes the point limit. takes effect on the next trim.
+    fn set_limit(&mut self, limit: usize);
+    fn limit(&self) -> usize;
+    /// all stored points, oldest first
+    fn points(&self) -> Vec<LidarPoint>;
+    /// removes all points, returning their entities so they can be despawned
+    fn clear(&mut self) -> Vec<Entity>;
+    /// points inside the axis aligned box from `min` to `max`, inclusive
+    fn query_aabb(&self, min: Vec3, max: Vec3) -> Vec<LidarPoint>;
+    /// side length of the voxels points are merged in, 0 if every point is kept
+    fn voxel_size(&self) -> f32;
+    /// changes the voxel size, re-indexing the stored points. points already sharing a voxel are kept.
+    fn set_voxel_size(&mut self, size: f32);
+    /// with a voxel size set, refreshes the stored point sharing a voxel with `point` instead of storing another.
+    /// the refreshed point counts as the newest one for trimming.
+    /// returns the refreshed point and its entity, or `None` if `point` should be added.
+    fn merge(&mut self, point: &LidarPoint) -> Option<(LidarPoint, Entity)>;
+}
+
+/// finds the stored point in each voxel of a grid, for `PointStorage::merge`.
+/// points are numbered in the order they were added, so a storage can find them in its oldest first queue.
+/// refreshed points keep their place in the queue until they reach the front, where they're moved to the back rather
+/// than trimmed, so refreshing doesn't shift every point behind them.
+#[derive(Clone, Default)]
+pub struct VoxelIndex {
+    /// 0 to not index anything
+    size: f32,
+    /// newest point in each voxel
+    voxels: HashMap<IVec3, usize>,
+    /// number of the oldest stored point
+    front: usize,
+    /// number the next point gets
+    back: usize,
+    /// numbers of the points refreshed since they were queued
+    refreshed: HashSet<usize>,
+}
+
+impl VoxelIndex {
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    fn key(&self, position: Vec3) -> IVec3 {
+        (position / self.size).floor().as_ivec3()
+    }
+
+    /// index in the oldest first queue of the point sharing a voxel with `position`
+    pub fn find(&self, position: Vec3) -> Option<usize> {
+        if self.size <= 0.0 {
+            return None;
+        }
+        let number = self.voxels.get(&self.key(position))?;
+        Some(number - self.front)
+    }
+
+    /// a point was added to the back of the queue
+    pub fn push(&mut self, position: Vec3) {
+        if self.size > 0.0 {
+            self.voxels.insert(self.key(position), self.back);
+        }
+        self.back += 1;
+    }
+
+    /// the point at `index` in the queue was refreshed
+    pub fn refresh(&mut self, index: usize) {
+        self.refreshed.insert(self.front + index);
+    }
+
+    /// the oldest point was removed from the queue.
+    /// returns whether it was refreshed since it was queued, in which case it belongs at the back, see `push`.
+    pub fn pop(&mut self, position: Vec3) -> bool {
+        if self.size > 0.0 {
+            let key = self.key(position);
+            if self.voxels.get(&key) == Some(&self.front) {
+                self.voxels.remove(&key);
+            }
+        }
+        let refreshed = self.refreshed.remove(&self.front);
+        self.front += 1;
+        refreshed
+    }
+
+    pub fn clear(&mut self) {
+        self.voxels.clear();
+        self.refreshed.clear();
+        self.front = 0;
+        self.back = 0;
+    }
+
+    /// indexes `positions`, oldest first, at a new voxel size
+    pub fn resize(&mut self, size: f32, positions: impl Iterator<Item = Vec3>) {
+        self.size = size;
+        self.clear();
+        for position in positions {
+            self.push(position);
+        }
+    }
+}
+
+/// a `PointStorage` that groups points into cubic leaves, so they can be drawn per leaf.
+/// see `lod::LodPlugin`.
+pub trait SpatialPointStorage: PointStorage {
+    /// side length of a leaf
+    fn leaf_size(&self) -> f32;
+    /// keys of the leaves that changed since the last call
+    fn take_dirty_leaves(&mut self) -> Vec<IVec3>;
+    /// points in a leaf, empty if it doesn't exist
+    fn leaf(&self, key: IVec3) -> &[(LidarPoint, Entity)];
+    fn leaf_summary(&self, key: IVec3) -> Option<PointSummary>;
+}
+
+/// aggregate of a group of points, for drawing them as one
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PointSummary {
+    pub count: usize,
+    pub position_sum: Vec3,
+    pub distance_sum: f32,
+    pub intensity_sum: f32,
+    /// most recently scanned point. points are trimmed oldest first, so this stays valid as points are removed.
+    pub newest: Option<LidarPoint>,
 }
 
+impl PointSummary {
+    pub fn add(&mut self, point: &LidarPoint) {
+        self.count += 1;
+        self.position_sum += point.position;
+        self.distance_sum += point.distance;
+        self.intensity_sum += point.intensity;
+        if self
+            .newest
+            .is_none_or(|newest| point.timestamp >= newest.timestamp)
+        {
+            self.newest = Some(*point);
+        }
+    }
+
+    pub fn remove(&mut self, point: &LidarPoint) {
+        self.count -= 1;
+        self.position_sum -= point.position;
+        self.distance_sum -= point.distance;
+        self.intensity_sum -= point.intensity;
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        self.position_sum / self.count.max(1) as f32
+    }
+
+    pub fn mean_distance(&self) -> f32 {
+        self.distance_sum / self.count.max(1) as f32
+    }
+
+    pub fn mean_intensity(&self) -> f32 {
+        self.intensity_sum / self.count.max(1) as f32
+    }
+}
+
+#[derive(Clone)]
 pub struct VecStorage {
-    pub points: VecDeque<Entity>,
+    pub points: VecDeque<(LidarPoint, Entity)>,
     pub limit: usize,
+    pub voxels: VoxelIndex,
+}
+
+impl VecStorage {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            points: VecDeque::new(),
+            limit,
+            voxels: VoxelIndex::default(),
+        }
+    }
 }
 
 impl PointStorage for VecStorage {
-    fn add_points(&mut self, _: &[Vec3], entities: &[Entity]) {
-        self.points.extend(entities.iter().cloned());
+    fn add_points(&mut self, points: &[LidarPoint], entities: &[Entity]) {
+        for point in points {
+            self.voxels.push(point.position);
+        }
+        self.points
+            .extend(points.iter().cloned().zip(entities.iter().cloned()));
     }
     fn trim(&mut self) -> Vec<Entity> {
-        let cur_len = self.points.len();
-        if cur_len > self.limit {
-            let excess_elements = cur_len - self.limit;
-            self.points.drain(0..excess_elements).collect()
-        } else {
-            vec![]
+        let mut trimmed = vec![];
+        while self.points.len() > self.limit {
+            let Some((point, entity)) = self.points.pop_front() else {
+                break;
+            };
+            if self.voxels.pop(point.position) {
+                self.voxels.push(point.position);
+                self.points.push_back((point, entity));
+            } else {
+                trimmed.push(entity);
+            }
         }
+        trimmed
+    }
+    fn len(&self) -> usize {
+        self.points.len()
+    }
+    fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+    fn limit(&self) -> usize {
+        self.limit
+    }
+    fn points(&self) -> Vec<LidarPoint> {
+        self.points.iter().map(|(point, _)| *point).collect()
+    }
+    fn clear(&mut self) -> Vec<Entity> {
+        self.voxels.clear();
+        self.points.drain(..).map(|(_, entity)| entity).collect()
+    }
+    fn query_aabb(&self, min: Vec3, max: Vec3) -> Vec<LidarPoint> {
+        self.points
+            .iter()
+            .map(|(point, _)| *point)
+            .filter(|point| point.position.cmpge(min).all() && point.position.cmple(max).all())
+            .collect()
+    }
+    fn voxel_size(&self) -> f32 {
+        self.voxels.size()
+    }
+    fn set_voxel_size(&mut self, size: f32) {
+        self.voxels
+            .resize(size, self.points.iter().map(|(point, _)| point.position));
+    }
+    fn merge(&mut self, point: &LidarPoint) -> Option<(LidarPoint, Entity)> {
+        let index = self.voxels.find(point.position)?;
+        let (stored, entity) = &mut self.points[index];
+        stored.refresh(point);
+        self.voxels.refresh(index);
+        Some((*stored, *entity))
     }
 }
 
@@ -38,16 +322,100 @@ pub struct Space<S: PointStorage> {
 }
 
 impl<S: PointStorage> Space<S> {
-    pub fn add_points(&mut self, points: &[Vec3], entities: &[Entity]) {
+    pub fn add_points(&mut self, points: &[LidarPoint], entities: &[Entity]) {
         self.accelerator.add_points(points, entities);
     }
 
     pub fn trim(&mut self) -> Vec<Entity> {
         self.accelerator.trim()
     }
+
+    pub fn merge(&mut self, point: &LidarPoint) -> Option<(LidarPoint, Entity)> {
+        self.accelerator.merge(point)
+    }
+
+    pub fn clear(&mut self) -> Vec<Entity> {
+        self.accelerator.clear()
+    }
+}
+
+/// a shot that hit nothing within range, so the direction is known to be empty rather than unscanned
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct LidarMiss {
+    pub origin: Vec3,
+    pub direction: Dir3,
+    /// `LidarGun::max_range` of the gun that fired
+    pub max_range: f32,
+    /// `Time<Virtual>` elapsed seconds when the shot was fired
+    pub timestamp: f32,
+    pub source: u32,
+}
+
+/// the most recent misses, oldest first. limited by `GameSettings::miss_log_limit`.
+#[derive(Resource, Default)]
+pub struct MissLog(pub VecDeque<LidarMiss>);
+
+impl MissLog {
+    pub fn record(&mut self, miss: LidarMiss, limit: usize) {
+        self.0.push_back(miss);
+        let excess = self.0.len().saturating_sub(limit);
+        self.0.drain(..excess);
+    }
+}
+
+/// shots waiting to be resolved, oldest first. `lidar_new_points` resolves up to `GameSettings::max_shots_per_frame`
+/// of them each frame and carries the rest over to later frames.
+#[derive(Resource, Default)]
+pub struct PendingShots(pub VecDeque<LidarShotFired>);
+
+impl PendingShots {
+    /// drops the oldest shots beyond `limit`, returning how many were dropped
+    pub fn drop_excess(&mut self, limit: usize) -> usize {
+        let excess = self.0.len().saturating_sub(limit);
+        self.0.drain(..excess);
+        excess
+    }
+}
+
+/// how many shots `lidar_new_points` can resolve within `GameSettings::shot_time_budget_ms`,
+/// learned from how long it took per shot in previous frames
+#[derive(Resource, Default)]
+pub struct ShotBudget {
+    /// smoothed seconds per resolved shot, 0 until the first shots are resolved
+    pub secs_per_shot: f32,
+}
+
+impl ShotBudget {
+    /// how quickly the estimate follows the latest frame
+    const SMOOTHING: f32 = 0.2;
+    /// shots allowed until a shot has been timed, few enough to fit any sensible budget
+    pub const UNMEASURED_SHOTS: usize = 64;
+
+    /// shots that fit in `budget_secs`, at least one and at most `max`. no budget allows `max`.
+    pub fn shots(&self, budget_secs: f32, max: usize) -> usize {
+        if budget_secs <= 0.0 {
+            return max;
+        }
+        if self.secs_per_shot <= 0.0 {
+            return Self::UNMEASURED_SHOTS.min(max);
+        }
+        ((budget_secs / self.secs_per_shot) as usize).max(1).min(max)
+    }
+
+    pub fn record(&mut self, shots: usize, secs: f32) {
+        if shots == 0 {
+            return;
+        }
+        let sample = secs / shots as f32;
+        self.secs_per_shot = if self.secs_per_shot > 0.0 {
+            self.secs_per_shot.lerp(sample, Self::SMOOTHING)
+        } else {
+            sample
+        };
+    }
 }
 
-/// tag for spheres created by the lidar shot system
+/// tag for points created by the lidar shot system
 #[derive(Component)]
 pub struct LidarTag;
 
@@ -55,80 +423,241 @@ pub struct LidarTag;
 #[derive(Component)]
 pub struct LidarInteractable;
 
+/// how much of the lidar's light a `LidarInteractable` surface sends back, from 0 to 1.
+/// surfaces without one are treated as fully reflective.
+#[derive(Component, Copy, Clone, Debug)]
+pub struct LidarReflectivity(pub f32);
+
+impl Default for LidarReflectivity {
+    fn default() -> Self {
+        LidarReflectivity(1.0)
+    }
+}
+
+/// simplified lidar equation: returns fall off with the cosine of the incidence angle and the square of the distance.
+/// `reference_distance` is the range up to which there's no distance falloff, and `incidence_falloff` blends
+/// between ignoring the incidence angle (0) and the full cosine falloff (1).
+pub fn return_intensity(
+    reflectivity: f32,
+    incidence_angle: f32,
+    distance: f32,
+    reference_distance: f32,
+    incidence_falloff: f32,
+) -> f32 {
+    let incidence_term = 1.0 + incidence_falloff * (incidence_angle.cos() - 1.0);
+    let range_term = if reference_distance > 0.0 {
+        (reference_distance / distance.max(reference_distance)).powi(2)
+    } else {
+        1.0
+    };
+    reflectivity.clamp(0.0, 1.0) * incidence_term * range_term
+}
+
 // #[derive(Component)]
 // pub struct ColorWrapper(Color);
 
+/// the shape every point is drawn with, and the material shared by all of them
 #[derive(Resource, Default, Clone)]
 pub struct SphereHandles {
     pub mesh: Option<Handle<Mesh>>,
     pub material: Option<Handle<CustomMaterial>>,
 }
 
+/// spawns the entity for a point. it's drawn as part of a `point_cloud::PointChunks` or `lod::LodChunks` chunk, not on its own.
+pub fn spawn_point(commands: &mut Commands, point: &LidarPoint) -> Entity {
+    commands
+        .spawn((*point, Transform::from_translation(point.position)))
+        .insert(LidarTag)
+        // .with_children(|children| {
+        //     children.spawn(PointLightBundle {
+        //         point_light: PointLight {
+        //             radius: light_radius,
+        //             color: Color::srgb(0.2, 0.2, 1.0),
+        //             ..default()
+        //         },
+        //         ..default()
+        //     });
+        // })
+        .id()
+}
+
 // TODO: optimize more.
 pub fn lidar_new_points<S: PointStorage + Send + Sync + 'static>(
-    mut raycast: MeshRayCast,
+    bvh: Res<SceneBvh>,
     mut commands: Commands,
     mut space: ResMut<Space<S>>,
-    filter_query_lidar_interactable: Query<(), With<LidarInteractable>>,
+    reflectivity_query: Query<&LidarReflectivity>,
     mut new_spheres: EventReader<LidarShotFired>,
-    sphere_handles: Res<SphereHandles>,
+    mut pending: ResMut<PendingShots>,
+    mut budget: ResMut<ShotBudget>,
+    mut resolved: EventWriter<LidarShotResolved>,
+    time: Res<Time>,
     game_settings: Res<GameSettings>,
+    mut unsaved: ResMut<UnsavedScan>,
+    mut rng: ResMut<LidarRng>,
+    noise: Res<LidarNoise>,
+    mut misses: ResMut<MissLog>,
+    mut diagnostics: Diagnostics,
 ) {
-    let Some(ref mesh) = sphere_handles.mesh else {
-        return;
-    };
-    let Some(ref material) = sphere_handles.material else {
-        return;
-    };
+    let start = Instant::now();
+    let mut hits = 0;
+    let mut added = 0;
+
+    pending.0.extend(new_spheres.read().copied());
+    let allowed = budget.shots(
+        game_settings.shot_time_budget_ms / 1000.0,
+        game_settings.max_shots_per_frame as usize,
+    );
+    let count = pending.0.len().min(allowed);
+    // noise is drawn in shot order before and after casting, so a seeded run doesn't depend on how the casts were scheduled
+    let shots: Vec<(LidarShotFired, Dir3)> = pending
+        .0
+        .drain(..count)
+        .map(|shot| (shot, noise.0.jitter_direction(&mut rng, shot.direction)))
+        .collect();
+    let dropped = pending.drop_excess(game_settings.max_pending_shots);
+    let rays: Vec<(Ray3d, f32)> = shots
+        .iter()
+        .map(|(shot, direction)| (Ray3d::new(shot.origin, *direction), shot.max_range))
+        .collect();
+    // only casting and resolving grows with the number of shots, so that's all the budget times
+    let resolve_start = Instant::now();
+    let casts = bvh.cast_rays(&rays);
+    let rays_cast = rays.len();
 
-    let light_radius = 1.0;
-
-    let mut new_points = Vec::new();
-    let mut new_entities = Vec::new();
-    let filter = |e| filter_query_lidar_interactable.contains(e);
-    let settings = RayCastSettings::default()
-        .with_visibility(RayCastVisibility::Visible)
-        .with_filter(&filter)
-        .always_early_exit();
-
-    for shot in new_spheres
-        .read()
-        .take(game_settings.max_shots_per_frame as usize)
-    {
-        let result = raycast
-            .cast_ray(
-                Ray3d::new(shot.origin, shot.direction),
-                &settings,
-                // &mut gizmos,
-            )
-            .first();
-        if let Some((_entity, data)) = result {
-            let entity = commands
-                .spawn((
-                    Mesh3d(mesh.clone()),
-                    MeshMaterial3d(material.clone()),
-                    Transform::from_translation(data.point).with_scale(Vec3::splat(light_radius)),
-                ))
-                .insert(LidarTag)
-                // .with_children(|children| {
-                //     children.spawn(PointLightBundle {
-                //         point_light: PointLight {
-                //             radius: light_radius,
-                //             color: Color::srgb(0.2, 0.2, 1.0),
-                //             ..default()
-                //         },
-                //         ..default()
-                //     });
-                // })
-                .id();
-            new_points.push(data.point);
-            new_entities.push(entity);
+    for ((shot, direction), hit) in shots.iter().zip(casts) {
+        let direction = *direction;
+        let point = hit.and_then(|hit| {
+            // triangle normals can face either way, make them face the gun
+            let mut normal = hit.normal;
+            if normal.dot(*direction) > 0.0 {
+                normal = -normal;
+            }
+            let incidence_angle = direction.dot(-normal).min(1.0).acos();
+            if !noise.0.returns(&mut rng, incidence_angle) {
+                return None;
+            }
+            let reflectivity = reflectivity_query
+                .get(hit.entity)
+                .copied()
+                .unwrap_or_default();
+            // the measured range is noisy, the surface it came from isn't
+            let distance = noise.0.measure_range(&mut rng, hit.distance);
+            Some(LidarPoint {
+                position: shot.origin + direction * distance,
+                normal,
+                distance,
+                incidence_angle,
+                intensity: return_intensity(
+                    reflectivity.0,
+                    incidence_angle,
+                    hit.distance,
+                    game_settings.intensity_reference_distance,
+                    game_settings.point_incidence_falloff,
+                ),
+                timestamp: time.elapsed_secs(),
+                source: shot.source,
+                returns: 1,
+            })
+        });
+
+        match point {
+            Some(point) => {
+                hits += 1;
+                // points are added one at a time, so later hits this frame can land in their voxels
+                match space.merge(&point) {
+                    Some((refreshed, entity)) => {
+                        commands.entity(entity).insert(refreshed);
+                    }
+                    None => {
+                        let entity = spawn_point(&mut commands, &point);
+                        space.add_points(&[point], &[entity]);
+                        added += 1;
+                    }
+                }
+            }
+            // dropped returns count as misses too, the sensor can't tell them apart
+            None if game_settings.miss_log_limit > 0 => {
+                misses.record(
+                    LidarMiss {
+                        origin: shot.origin,
+                        direction,
+                        max_range: shot.max_range,
+                        timestamp: time.elapsed_secs(),
+                        source: shot.source,
+                    },
+                    game_settings.miss_log_limit,
+                );
+                // misses are saved too
+                unsaved.0 = true;
+            }
+            None => {}
         }
+        resolved.send(LidarShotResolved {
+            origin: shot.origin,
+            direction,
+            hit: point.map(|point| point.position),
+            max_range: shot.max_range,
+        });
+    }
+    budget.record(rays_cast, resolve_start.elapsed().as_secs_f32());
+    if hits > 0 {
+        unsaved.0 = true;
     }
-    space.add_points(&new_points[..], &new_entities[..]);
-    for entity in space.trim() {
+    let trimmed = space.trim();
+    for &entity in &trimmed {
         commands.entity(entity).despawn_recursive();
     }
+
+    let elapsed = start.elapsed();
+    diagnostics.add_measurement(&diagnostics::SHOTS_ALLOWED, || allowed as f64);
+    diagnostics.add_measurement(&diagnostics::RAYS_CAST, || rays_cast as f64);
+    diagnostics.add_measurement(&diagnostics::SHOTS_PENDING, || pending.0.len() as f64);
+    diagnostics.add_measurement(&diagnostics::SHOTS_DROPPED, || dropped as f64);
+    diagnostics.add_measurement(&diagnostics::HITS, || hits as f64);
+    diagnostics.add_measurement(&diagnostics::MISSES, || (rays_cast - hits) as f64);
+    diagnostics.add_measurement(&diagnostics::POINTS_ADDED, || added as f64);
+    diagnostics.add_measurement(&diagnostics::POINTS_MERGED, || (hits - added) as f64);
+    diagnostics.add_measurement(&diagnostics::POINTS_TRIMMED, || trimmed.len() as f64);
+    diagnostics.add_measurement(&diagnostics::SHOT_TIME, || {
+        elapsed.as_secs_f64() * 1000.0
+    });
+}
+
+/// applies `GameSettings::voxel_size` to the storage when it changes
+pub fn sync_voxel_size<S: PointStorage + Send + Sync + 'static>(
+    settings: Res<GameSettings>,
+    mut space: ResMut<Space<S>>,
+) {
+    if settings.is_changed() && space.accelerator.voxel_size() != settings.voxel_size {
+        space.accelerator.set_voxel_size(settings.voxel_size);
+    }
+}
+
+/// keeps lidar points in `S`, turning `LidarShotFired` into points.
+/// drawing them is up to `point_cloud::PointCloudPlugin` or `lod::LodPlugin`, saving them and the other systems that
+/// depend on `S` are added by `LidarPlugin`.
+pub struct SpacePlugin<S> {
+    /// initial storage, its limit is overridden by points_limit from game_config or the command line
+    pub accelerator: S,
+}
+
+impl<S: PointStorage + Clone + Send + Sync + 'static> Plugin for SpacePlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Space {
+            accelerator: self.accelerator.clone(),
+        })
+        .add_plugins(BvhPlugin)
+        .insert_resource(MissLog::default())
+        .insert_resource(PendingShots::default())
+        .insert_resource(ShotBudget::default())
+        .insert_resource(LidarNoise::default())
+        .add_systems(Update, (sync_noise_model, sync_voxel_size::<S>))
+        .add_systems(
+            Update,
+            lidar_new_points::<S>.run_if(in_state(InGameState::Active)),
+        );
+    }
 }
 
 // pub fn propagate_update_colors(
@@ -0,0 +1,94 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{gamestate::GameState, gun::LidarShotResolved, settings::UserSettings};
+
+/// where tracers start, relative to the camera. shots come from the camera itself,
+/// and a line starting there would be seen end on.
+const MUZZLE_OFFSET: Vec3 = Vec3::new(0.2, -0.2, -0.4);
+const TRACER_COLOR: LinearRgba = LinearRgba::rgb(0.3, 0.6, 1.0);
+
+struct Tracer {
+    start: Vec3,
+    end: Vec3,
+    /// `Time<Virtual>` elapsed seconds when the shot resolved
+    fired: f32,
+}
+
+#[derive(Resource, Default)]
+pub struct Tracers(VecDeque<Tracer>);
+
+/// all tracers are drawn in one gizmo pass
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct TracerGizmos;
+
+fn record_tracers(
+    mut tracers: ResMut<Tracers>,
+    mut shots: EventReader<LidarShotResolved>,
+    settings: Res<UserSettings>,
+    time: Res<Time>,
+    camera: Query<&GlobalTransform, With<Camera3d>>,
+) {
+    let settings = &settings.tracers;
+    let Ok(camera) = camera.get_single() else {
+        shots.clear();
+        return;
+    };
+    if !settings.enabled {
+        shots.clear();
+        tracers.0.clear();
+        return;
+    }
+
+    let start = camera.transform_point(MUZZLE_OFFSET);
+    for shot in shots.read() {
+        let miss_length = settings.miss_length.min(shot.max_range);
+        tracers.0.push_back(Tracer {
+            start,
+            end: shot.hit.unwrap_or(shot.origin + shot.direction * miss_length),
+            fired: time.elapsed_secs(),
+        });
+    }
+    let excess = tracers.0.len().saturating_sub(settings.max_tracers);
+    tracers.0.drain(..excess);
+}
+
+fn draw_tracers(
+    mut tracers: ResMut<Tracers>,
+    mut gizmos: Gizmos<TracerGizmos>,
+    settings: Res<UserSettings>,
+    time: Res<Time>,
+) {
+    let settings = &settings.tracers;
+    let now = time.elapsed_secs();
+    while tracers
+        .0
+        .front()
+        .is_some_and(|tracer| now - tracer.fired > settings.lifetime)
+    {
+        tracers.0.pop_front();
+    }
+
+    for tracer in &tracers.0 {
+        let fade = 1.0 - (now - tracer.fired) / settings.lifetime.max(f32::EPSILON);
+        let color = TRACER_COLOR * settings.intensity * fade;
+        gizmos.line(tracer.start, tracer.end, color.with_alpha(fade));
+    }
+}
+
+/// short lived lines from the gun to where each shot landed, see `settings::TracerSettings`
+pub struct TracerPlugin;
+
+impl Plugin for TracerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Tracers>()
+            .init_gizmo_group::<TracerGizmos>()
+            .add_systems(
+                Update,
+                (record_tracers, draw_tracers)
+                    .chain()
+                    .run_if(in_state(GameState::InGame)),
+            );
+    }
+}
@@ -1,13 +1,29 @@
 use bevy::prelude::*;
-use rand;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use std::f32::consts::TAU;
 
+/// the rng all gameplay randomness should draw from, so that a run can be reproduced from its seed
+#[derive(Resource, Deref, DerefMut)]
+pub struct LidarRng(pub StdRng);
+
+impl LidarRng {
+    pub fn seeded(seed: u64) -> Self {
+        LidarRng(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Default for LidarRng {
+    fn default() -> Self {
+        LidarRng::seeded(rand::random())
+    }
+}
+
 /// samples a direction within a specific solid angle, specified by the angular radius.
 /// centered around Vec3::Z
-pub fn solid_angle_sample(angular_radius_radians: f32) -> Vec3 {
+pub fn solid_angle_sample(rng: &mut impl Rng, angular_radius_radians: f32) -> Vec3 {
     let cos = angular_radius_radians.cos();
-    let u: f32 = rand::random();
-    let v: f32 = rand::random();
+    let u: f32 = rng.random();
+    let v: f32 = rng.random();
     let (mut y, mut x) = (TAU * u).sin_cos();
     let z: f32 = 1.0 + v * (cos - 1.0);
     let r = (1.0 - z.powi(2)).sqrt();
@@ -15,3 +31,11 @@ pub fn solid_angle_sample(angular_radius_radians: f32) -> Vec3 {
     y *= r;
     Vec3::new(x, y, z)
 }
+
+/// standard normal sample, using the box-muller transform
+pub fn gaussian(rng: &mut impl Rng) -> f32 {
+    // 1 - u keeps the log argument in (0, 1]
+    let u: f32 = 1.0 - rng.random::<f32>();
+    let v: f32 = rng.random();
+    (-2.0 * u.ln()).sqrt() * (TAU * v).cos()
+}
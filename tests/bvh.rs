@@ -0,0 +1,90 @@
+use bevy::{
+    prelude::*,
+    tasks::{ComputeTaskPool, TaskPool},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use lidar::bvh::SceneBvh;
+
+/// a unit cube at the origin, and a 20 by 20 ground plane below it
+fn scene() -> SceneBvh {
+    let cube = Mesh::from(Cuboid::new(1.0, 1.0, 1.0));
+    let ground = Plane3d::default()
+        .mesh()
+        .size(20.0, 20.0)
+        .subdivisions(8)
+        .build();
+    let cube_transform = GlobalTransform::IDENTITY;
+    let ground_transform = GlobalTransform::from_xyz(0.0, -0.5, 0.0);
+    SceneBvh::from_meshes([
+        (Entity::from_raw(0), &cube, &cube_transform),
+        (Entity::from_raw(1), &ground, &ground_transform),
+    ])
+}
+
+#[test]
+fn hits_the_nearest_front_face() {
+    let bvh = scene();
+    assert_eq!(bvh.triangle_count(), 12 + 2 * 9 * 9);
+
+    let hit = bvh
+        .cast_ray(Ray3d::new(Vec3::new(-5.0, 0.0, 0.0), Dir3::X), 100.0)
+        .expect("ray points at the cube");
+    assert_eq!(hit.entity, Entity::from_raw(0));
+    assert!((hit.distance - 4.5).abs() < 1e-5);
+    assert!(hit.normal.abs_diff_eq(Vec3::NEG_X, 1e-5));
+
+    let hit = bvh
+        .cast_ray(Ray3d::new(Vec3::new(5.0, 5.0, 5.0), Dir3::NEG_Y), 100.0)
+        .expect("ray points at the ground");
+    assert_eq!(hit.entity, Entity::from_raw(1));
+    assert!((hit.distance - 5.5).abs() < 1e-5);
+}
+
+#[test]
+fn respects_max_distance_and_backfaces() {
+    let bvh = scene();
+    assert!(bvh
+        .cast_ray(Ray3d::new(Vec3::new(-5.0, 0.0, 0.0), Dir3::X), 4.0)
+        .is_none());
+    // the ground is one sided, like it is for `MeshRayCast`
+    assert!(bvh
+        .cast_ray(Ray3d::new(Vec3::new(5.0, -5.0, 5.0), Dir3::Y), 100.0)
+        .is_none());
+}
+
+#[test]
+fn parallel_casts_match_single_casts_in_order() {
+    ComputeTaskPool::get_or_init(TaskPool::default);
+    let bvh = scene();
+    let mut rng = StdRng::seed_from_u64(7);
+    let rays: Vec<(Ray3d, f32)> = (0..1000)
+        .map(|_| {
+            let origin = Vec3::new(
+                -8.0,
+                rng.random_range(0.0..4.0),
+                rng.random_range(-4.0..4.0),
+            );
+            let target = Vec3::new(
+                rng.random_range(-1.0..1.0),
+                -0.5,
+                rng.random_range(-1.0..1.0),
+            );
+            (
+                Ray3d::new(origin, Dir3::new(target - origin).unwrap()),
+                50.0,
+            )
+        })
+        .collect();
+
+    let parallel = bvh.cast_rays(&rays);
+    assert_eq!(parallel.len(), rays.len());
+    for (&(ray, max_distance), hit) in rays.iter().zip(&parallel) {
+        let single = bvh.cast_ray(ray, max_distance);
+        assert_eq!(
+            hit.map(|hit| (hit.entity, hit.distance)),
+            single.map(|hit| (hit.entity, hit.distance))
+        );
+    }
+    assert!(parallel.iter().filter(|hit| hit.is_some()).count() > 900);
+}
@@ -0,0 +1,273 @@
+//! a headless `App` running the gameplay systems, with helpers to drive input and time.
+//! each test binary only uses some of it.
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use bevy::{
+    diagnostic::DiagnosticsStore,
+    input::mouse::{MouseMotion, MouseWheel},
+    prelude::*,
+    render::primitives::Aabb,
+    state::app::StatesPlugin,
+    time::TimeUpdateStrategy,
+    transform::TransformPlugin,
+};
+
+use lidar::{
+    assets::{loading_update, AssetsTracking, SettingsHandles},
+    bvh::BvhPlugin,
+    cli::Cli,
+    gamestate::GameState,
+    gun::{
+        lidar_basic_shot_system, lidar_spread_sync, LidarGun, LidarShotFired, LidarShotResolved,
+    },
+    input::{player_firing_sync, player_input_system, PlayerInput},
+    noise::LidarNoise,
+    player::{player_movement_system, Player},
+    save::UnsavedScan,
+    settings::{GameSettings, GunHeatSettings, UserSettings},
+    space::{
+        lidar_new_points, sync_voxel_size, LidarInteractable, LidarTag, MissLog, PendingShots,
+        ShotBudget, Space, VecStorage,
+    },
+    util::LidarRng,
+};
+
+/// length of a frame, time only advances through `TestApp::advance`
+pub const FRAME: Duration = Duration::from_nanos(16_666_667);
+
+/// number of `LidarShotFired` events sent so far
+#[derive(Resource, Default)]
+pub struct ShotsFired(pub usize);
+
+fn count_shots(mut shots: EventReader<LidarShotFired>, mut fired: ResMut<ShotsFired>) {
+    fired.0 += shots.read().count();
+}
+
+/// stands in for the `InputPlugin`, which would clear presses made by tests before systems see them
+fn clear_input(
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut mouse: ResMut<ButtonInput<MouseButton>>,
+) {
+    keys.clear();
+    mouse.clear();
+}
+
+/// settings that make results easy to predict: no spin up, no overheating, no noise
+pub fn test_settings() -> GameSettings {
+    GameSettings {
+        gun_fire_rate: 600.0,
+        gun_heat: GunHeatSettings {
+            min_rate_fraction: 1.0,
+            overheat_secs: 0.0,
+            ..default()
+        },
+        gun_max_range: 100.0,
+        miss_log_limit: 1000,
+        max_shots_per_frame: 10_000,
+        max_pending_shots: 10_000,
+        max_gun_spread: 1.0,
+        movement_speed_factor: 10.0,
+        seed: Some(0),
+        ..default()
+    }
+}
+
+pub struct TestApp(pub App);
+
+impl std::ops::Deref for TestApp {
+    type Target = App;
+    fn deref(&self) -> &App {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for TestApp {
+    fn deref_mut(&mut self) -> &mut App {
+        &mut self.0
+    }
+}
+
+impl TestApp {
+    fn base() -> Self {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME));
+        TestApp(app)
+    }
+
+    /// input, movement, shooting and `lidar_new_points` with a `VecStorage`, in the order `LidarPlugin` runs them.
+    /// transforms propagate like they do in the game. there is no player or level until the test spawns them.
+    pub fn new() -> Self {
+        let mut test = Self::base();
+        test.add_plugins(TransformPlugin)
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .add_event::<MouseMotion>()
+            .add_event::<MouseWheel>()
+            .add_event::<LidarShotFired>()
+            .add_event::<LidarShotResolved>()
+            .insert_resource(Assets::<Mesh>::default())
+            .add_plugins(BvhPlugin)
+            .init_resource::<DiagnosticsStore>()
+            .insert_resource(UserSettings {
+                sensitivity: Vec2::splat(0.01),
+                fov: 90.0,
+                ..default()
+            })
+            .insert_resource(test_settings())
+            .insert_resource(Space {
+                accelerator: VecStorage::new(100_000),
+            })
+            .insert_resource(LidarRng::seeded(0))
+            .init_resource::<PlayerInput>()
+            .init_resource::<LidarNoise>()
+            .init_resource::<MissLog>()
+            .init_resource::<PendingShots>()
+            .init_resource::<ShotBudget>()
+            .init_resource::<UnsavedScan>()
+            .init_resource::<ShotsFired>()
+            .add_systems(PreUpdate, player_input_system)
+            .add_systems(
+                Update,
+                (
+                    player_movement_system,
+                    lidar_basic_shot_system,
+                    player_firing_sync,
+                    lidar_spread_sync,
+                    count_shots,
+                    sync_voxel_size::<VecStorage>,
+                    lidar_new_points::<VecStorage>,
+                )
+                    .chain(),
+            )
+            .add_systems(Last, clear_input);
+        test
+    }
+
+    /// `loading_update` with nothing tracked yet, and the given settings standing in for the loaded files
+    pub fn loading(cli: Cli, user: UserSettings, game: GameSettings) -> Self {
+        let mut test = Self::base();
+        test.add_plugins(AssetPlugin::default())
+            .init_asset::<UserSettings>()
+            .init_asset::<GameSettings>()
+            .init_state::<GameState>()
+            .insert_resource(cli)
+            .init_resource::<UserSettings>()
+            .init_resource::<GameSettings>()
+            .insert_resource(AssetsTracking::new())
+            .add_systems(Update, loading_update.run_if(in_state(GameState::Loading)));
+        let user = test
+            .world_mut()
+            .resource_mut::<Assets<UserSettings>>()
+            .add(user);
+        let game = test
+            .world_mut()
+            .resource_mut::<Assets<GameSettings>>()
+            .add(game);
+        test.insert_resource(SettingsHandles { user, game });
+        test
+    }
+
+    pub fn advance(&mut self, frames: u32) {
+        for _ in 0..frames {
+            self.update();
+        }
+    }
+
+    pub fn advance_secs(&mut self, secs: f32) {
+        self.advance((secs / FRAME.as_secs_f32()).round() as u32);
+    }
+
+    pub fn spawn_player(&mut self, transform: Transform, gun: LidarGun) -> Entity {
+        self.world_mut().spawn((Player, transform, gun)).id()
+    }
+
+    /// spawns a `LidarInteractable` mesh. nothing computes bounds or visibility here, so they're filled in directly.
+    pub fn spawn_target(&mut self, mesh: impl Into<Mesh>, transform: Transform) -> Entity {
+        let mesh = mesh.into();
+        let aabb: Aabb = mesh.compute_aabb().expect("target mesh has positions");
+        let mesh = self.world_mut().resource_mut::<Assets<Mesh>>().add(mesh);
+        self.world_mut()
+            .spawn((
+                Mesh3d(mesh),
+                transform,
+                GlobalTransform::from(transform),
+                aabb,
+                Visibility::Visible,
+                InheritedVisibility::VISIBLE,
+                LidarInteractable,
+            ))
+            .id()
+    }
+
+    pub fn press(&mut self, key: KeyCode) {
+        self.world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(key);
+    }
+
+    pub fn release(&mut self, key: KeyCode) {
+        self.world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .release(key);
+    }
+
+    pub fn press_mouse(&mut self, button: MouseButton) {
+        self.world_mut()
+            .resource_mut::<ButtonInput<MouseButton>>()
+            .press(button);
+    }
+
+    pub fn release_mouse(&mut self, button: MouseButton) {
+        self.world_mut()
+            .resource_mut::<ButtonInput<MouseButton>>()
+            .release(button);
+    }
+
+    pub fn move_mouse(&mut self, delta: Vec2) {
+        self.world_mut().send_event(MouseMotion { delta });
+    }
+
+    pub fn settings_mut(&mut self) -> Mut<'_, GameSettings> {
+        self.world_mut().resource_mut::<GameSettings>()
+    }
+
+    pub fn player_transform(&mut self) -> Transform {
+        *self
+            .world_mut()
+            .query_filtered::<&Transform, With<Player>>()
+            .single(self.world())
+    }
+
+    pub fn player_gun(&mut self) -> LidarGun {
+        self.world_mut()
+            .query_filtered::<&LidarGun, With<Player>>()
+            .single(self.world())
+            .clone()
+    }
+
+    pub fn shots_fired(&self) -> usize {
+        self.world().resource::<ShotsFired>().0
+    }
+
+    pub fn point_count(&self) -> usize {
+        self.world()
+            .resource::<Space<VecStorage>>()
+            .accelerator
+            .points
+            .len()
+    }
+
+    pub fn point_entity_count(&mut self) -> usize {
+        self.world_mut()
+            .query_filtered::<(), With<LidarTag>>()
+            .iter(self.world())
+            .count()
+    }
+
+    pub fn state(&self) -> GameState {
+        self.world().resource::<State<GameState>>().get().clone()
+    }
+}
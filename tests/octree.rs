@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+
+use lidar::{
+    octree::OctreeStorage,
+    space::{LidarPoint, PointStorage, SpatialPointStorage},
+};
+
+fn point_at(position: Vec3, intensity: f32, timestamp: f32) -> LidarPoint {
+    LidarPoint {
+        position,
+        normal: Vec3::Y,
+        distance: 1.0,
+        incidence_angle: 0.0,
+        intensity,
+        timestamp,
+        source: 0,
+        returns: 1,
+    }
+}
+
+#[test]
+fn voxels_average_their_returns() {
+    let mut octree = OctreeStorage::new(8.0, 10);
+    octree.set_voxel_size(1.0);
+    let a = Entity::from_raw(1);
+    octree.add_points(&[point_at(Vec3::splat(0.2), 1.0, 0.0)], &[a]);
+    octree.take_dirty_leaves();
+
+    let (refreshed, entity) = octree
+        .merge(&point_at(Vec3::splat(0.8), 0.0, 1.0))
+        .expect("same voxel");
+    assert_eq!(entity, a);
+    assert_eq!(refreshed.position, Vec3::splat(0.2));
+    assert_eq!(refreshed.timestamp, 1.0);
+    assert_eq!(refreshed.returns, 2);
+    assert_eq!(refreshed.intensity, 0.5);
+    assert_eq!(octree.len(), 1);
+    assert_eq!(octree.take_dirty_leaves(), vec![IVec3::ZERO]);
+
+    // a plain mean, the first return still counts after many more
+    for timestamp in 2..10 {
+        octree.merge(&point_at(Vec3::splat(0.5), 0.0, timestamp as f32));
+    }
+    let (refreshed, _) = octree
+        .merge(&point_at(Vec3::splat(0.5), 0.0, 10.0))
+        .expect("same voxel");
+    assert_eq!(refreshed.returns, 10);
+    assert!((refreshed.intensity - 0.1).abs() < 1e-6);
+    let summary = octree.leaf_summary(IVec3::ZERO).unwrap();
+    assert_eq!(summary.count, 1);
+    assert!((summary.mean_intensity() - refreshed.intensity).abs() < 1e-6);
+    assert_eq!(octree.points()[0].returns, 10);
+}
+
+#[test]
+fn voxels_follow_trimming() {
+    let mut octree = OctreeStorage::new(8.0, 1);
+    octree.set_voxel_size(1.0);
+    let (a, b) = (Entity::from_raw(1), Entity::from_raw(2));
+    octree.add_points(&[point_at(Vec3::splat(0.2), 1.0, 0.0)], &[a]);
+    octree.add_points(&[point_at(Vec3::splat(1.5), 1.0, 1.0)], &[b]);
+    assert_eq!(octree.trim(), vec![a]);
+
+    assert!(octree
+        .merge(&point_at(Vec3::splat(0.2), 1.0, 2.0))
+        .is_none());
+    assert_eq!(
+        octree
+            .merge(&point_at(Vec3::splat(1.2), 1.0, 2.0))
+            .map(|(_, entity)| entity),
+        Some(b)
+    );
+}
+
+#[test]
+fn refreshed_points_are_trimmed_last() {
+    let mut octree = OctreeStorage::new(8.0, 2);
+    octree.set_voxel_size(1.0);
+    let (a, b, c) = (
+        Entity::from_raw(1),
+        Entity::from_raw(2),
+        Entity::from_raw(3),
+    );
+    octree.add_points(&[point_at(Vec3::splat(0.5), 1.0, 0.0)], &[a]);
+    octree.add_points(&[point_at(Vec3::splat(1.5), 1.0, 1.0)], &[b]);
+    octree.merge(&point_at(Vec3::splat(0.5), 1.0, 2.0));
+    octree.add_points(&[point_at(Vec3::splat(2.5), 1.0, 3.0)], &[c]);
+    assert_eq!(octree.trim(), vec![b]);
+
+    // c took b's place in the leaf, merging into it has to find it there
+    octree.merge(&point_at(Vec3::splat(2.5), 0.0, 4.0));
+    let leaf = octree.leaf(IVec3::ZERO);
+    assert_eq!(leaf.len(), 2);
+    for (point, entity) in leaf {
+        assert_eq!(point.returns, 2, "{entity:?}");
+    }
+    assert_eq!(
+        octree
+            .merge(&point_at(Vec3::splat(0.5), 1.0, 5.0))
+            .map(|(_, entity)| entity),
+        Some(a)
+    );
+}
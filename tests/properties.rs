@@ -0,0 +1,47 @@
+use std::f32::consts::PI;
+
+use proptest::{collection::vec, prelude::*};
+use rand::{rngs::StdRng, SeedableRng};
+
+use lidar::{gun::LidarGun, settings::GunHeatSettings, util::solid_angle_sample};
+
+/// a gun that always fires at its full rate and never overheats
+fn steady() -> GunHeatSettings {
+    GunHeatSettings {
+        min_rate_fraction: 1.0,
+        overheat_secs: 0.0,
+        ..Default::default()
+    }
+}
+
+proptest! {
+    #[test]
+    fn solid_angle_sample_stays_within_cone(seed: u64, radius in 0.0f32..PI) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        for _ in 0..32 {
+            let sample = solid_angle_sample(&mut rng, radius);
+            prop_assert!((sample.length() - 1.0).abs() < 1e-4, "{sample} isn't normalized");
+            let angle = sample.z.clamp(-1.0, 1.0).acos();
+            prop_assert!(angle <= radius + 1e-3, "{sample} is {angle} from the axis");
+        }
+    }
+
+    #[test]
+    fn shoot_conserves_time(rate in 1.0f32..2000.0, frames in vec(0.0f32..0.1, 1..200)) {
+        let heat = steady();
+        let mut gun = LidarGun::new(0.1, rate, 100.0);
+        let mut shots = 0;
+        // summed in f64, so rounding in the reference doesn't add up over hundreds of frames
+        let mut held = 0.0f64;
+        let rate = rate as f64;
+        for delta in frames {
+            gun.charge(delta, &heat);
+            held += delta as f64;
+            shots += gun.shoot(&heat);
+            // never fires ahead of the time the trigger was held
+            prop_assert!(shots as f64 <= held * rate + 1e-2, "{shots} shots in {held}s");
+        }
+        // and whatever isn't fired yet is still banked, not lost
+        prop_assert!(held * rate - (shots as f64) < 1.0 + 1e-2, "{shots} shots in {held}s");
+    }
+}
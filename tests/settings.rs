@@ -0,0 +1,46 @@
+use bevy::scene::ron;
+use lidar::settings::{GameSettings, NoiseModelKind, NoiseSettings};
+
+#[test]
+fn shipped_game_config_parses() {
+    let settings: GameSettings =
+        ron::from_str(include_str!("../assets/game.rconfig")).expect("game.rconfig parses");
+    // scanner profiles set these, the defaults are only for a missing profile
+    assert_eq!(settings.gun_fire_rate, 1000.0);
+    assert_eq!(settings.gun_max_range, 100.0);
+    assert_eq!(settings.noise.model, NoiseModelKind::None);
+}
+
+/// an override in the config dir, written before most settings existed
+#[test]
+fn old_config_overrides_fall_back_to_defaults() {
+    let settings: GameSettings = ron::from_str(
+        "(
+            color_distance_factor: 0.0,
+            gun_fire_rate: 500.0,
+            max_shots_per_frame: 1000,
+            max_gun_spread: 0.5,
+            movement_speed_factor: 8.0,
+            points_limit: None,
+        )",
+    )
+    .expect("old override parses");
+    assert_eq!(settings.gun_fire_rate, 500.0);
+    assert_eq!(settings.gun_max_range, 100.0);
+    assert_eq!(settings.gun_heat.min_rate_fraction, 0.25);
+    assert_eq!(settings.noise.model, NoiseModelKind::None);
+    assert_eq!(settings.voxel_size, 0.0);
+}
+
+#[test]
+fn partial_noise_settings_keep_sane_defaults() {
+    let settings: GameSettings =
+        ron::from_str("(noise: (model: Gaussian))").expect("partial noise block parses");
+    let defaults = NoiseSettings::default();
+    assert_eq!(settings.noise.model, NoiseModelKind::Gaussian);
+    assert_eq!(
+        settings.noise.max_incidence_angle,
+        defaults.max_incidence_angle
+    );
+    assert!(settings.noise.max_incidence_angle > 1.0);
+}
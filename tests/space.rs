@@ -0,0 +1,63 @@
+use bevy::prelude::*;
+
+use lidar::space::{LidarPoint, PointStorage, ShotBudget, VecStorage};
+
+#[test]
+fn shot_budget_follows_the_measured_cost() {
+    let mut budget = ShotBudget::default();
+    // nothing measured yet
+    assert_eq!(budget.shots(0.004, 1000), ShotBudget::UNMEASURED_SHOTS);
+    assert_eq!(budget.shots(0.004, 10), 10);
+    assert_eq!(budget.shots(0.0, 1000), 1000);
+
+    // 1/1024 s per shot
+    budget.record(64, 0.0625);
+    assert_eq!(budget.shots(0.25, 1000), 256);
+    assert_eq!(budget.shots(0.25, 150), 150);
+    assert_eq!(budget.shots(0.0, 1000), 1000);
+
+    // slower frames shrink it, but it never stops shooting
+    for _ in 0..100 {
+        budget.record(10, 1.0);
+    }
+    assert_eq!(budget.shots(0.004, 1000), 1);
+}
+
+#[test]
+fn refreshed_points_are_trimmed_last() {
+    let point = |x: f32| LidarPoint {
+        position: Vec3::new(x, 0.5, 0.5),
+        normal: Vec3::Y,
+        distance: 1.0,
+        incidence_angle: 0.0,
+        intensity: 1.0,
+        timestamp: x,
+        source: 0,
+        returns: 1,
+    };
+    let mut storage = VecStorage::new(2);
+    storage.set_voxel_size(1.0);
+    let (a, b, c) = (
+        Entity::from_raw(1),
+        Entity::from_raw(2),
+        Entity::from_raw(3),
+    );
+    storage.add_points(&[point(0.5), point(1.5)], &[a, b]);
+    assert_eq!(
+        storage.merge(&point(0.6)).map(|(_, entity)| entity),
+        Some(a)
+    );
+    storage.add_points(&[point(2.5)], &[c]);
+
+    assert_eq!(storage.trim(), vec![b]);
+    assert_eq!(storage.len(), 2);
+    assert_eq!(
+        storage.merge(&point(0.7)).map(|(_, entity)| entity),
+        Some(a)
+    );
+    assert_eq!(
+        storage.merge(&point(2.6)).map(|(_, entity)| entity),
+        Some(c)
+    );
+    assert!(storage.merge(&point(1.6)).is_none());
+}
@@ -0,0 +1,373 @@
+mod common;
+
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+
+use common::{test_settings, TestApp, FRAME};
+use lidar::{
+    assets::{AssetsTracking, SettingsLoaded},
+    bvh::SceneBvh,
+    cli::Cli,
+    gamestate::GameState,
+    gun::LidarGun,
+    player::PLAYER_RADIUS,
+    save::UnsavedScan,
+    scanner::ScanPattern,
+    settings::{GameSettings, UserSettings},
+    space::{LidarInteractable, LidarPoint, MissLog, PendingShots, Space, VecStorage},
+};
+
+/// a player at the origin, aiming along +x like a freshly spawned one
+fn player(test: &mut TestApp) {
+    test.spawn_player(Transform::IDENTITY, LidarGun::new(0.05, 600.0, 100.0));
+}
+
+/// a 20 by 20 wall whose near face is at x = 9.5
+fn wall(test: &mut TestApp) {
+    test.spawn_target(
+        Cuboid::new(1.0, 20.0, 20.0),
+        Transform::from_xyz(10.0, 0.0, 0.0),
+    );
+}
+
+#[test]
+fn walks_forward_at_movement_speed() {
+    let mut test = TestApp::new();
+    player(&mut test);
+    test.press(KeyCode::KeyW);
+    test.advance_secs(1.0);
+
+    let translation = test.player_transform().translation;
+    let speed = test_settings().movement_speed_factor;
+    assert!(
+        (translation.x - speed).abs() < speed * FRAME.as_secs_f32() * 2.0,
+        "moved to {translation}"
+    );
+    assert!(translation.y.abs() < 1e-4 && translation.z.abs() < 1e-4);
+}
+
+#[test]
+fn flies_up_and_stops_on_release() {
+    let mut test = TestApp::new();
+    player(&mut test);
+    test.press(KeyCode::Space);
+    test.advance(10);
+    test.release(KeyCode::Space);
+    test.advance(1);
+    let height = test.player_transform().translation.y;
+    assert!(height > 0.0);
+
+    test.advance(10);
+    assert_eq!(test.player_transform().translation.y, height);
+}
+
+#[test]
+fn mouse_motion_turns_the_player() {
+    let mut test = TestApp::new();
+    player(&mut test);
+    test.move_mouse(Vec2::new(10.0, 0.0));
+    test.advance(1);
+
+    // sensitivity is 0.01, moving right turns clockwise seen from above
+    let (yaw, _, _) = test.player_transform().rotation.to_euler(EulerRot::YXZ);
+    assert!((yaw + 0.1).abs() < 1e-4, "yaw is {yaw}");
+
+    // motion only applies on the frame it happened
+    test.advance(5);
+    let (still, _, _) = test.player_transform().rotation.to_euler(EulerRot::YXZ);
+    assert!((still - yaw).abs() < 1e-6);
+}
+
+#[test]
+fn spread_grows_and_clamps() {
+    let mut test = TestApp::new();
+    player(&mut test);
+    test.press(KeyCode::KeyE);
+    test.advance(10);
+    let spread = test.player_gun().current_angular_spread_radius;
+    assert!(spread > 0.05);
+
+    test.advance(1000);
+    let spread = test.player_gun().current_angular_spread_radius;
+    assert_eq!(spread, test_settings().max_gun_spread);
+}
+
+#[test]
+fn fires_at_the_gun_rate_while_held() {
+    let mut test = TestApp::new();
+    player(&mut test);
+    test.press_mouse(MouseButton::Left);
+    test.advance_secs(1.0);
+
+    // firing starts the frame after the trigger is pulled
+    let fired = test.shots_fired() as f32;
+    assert!(
+        (fired - 600.0).abs() <= 600.0 * FRAME.as_secs_f32() + 1.0,
+        "fired {fired}"
+    );
+
+    test.release_mouse(MouseButton::Left);
+    test.advance(2);
+    let fired = test.shots_fired();
+    test.advance(10);
+    assert_eq!(test.shots_fired(), fired);
+}
+
+#[test]
+fn hits_become_points_on_the_wall() {
+    let mut test = TestApp::new();
+    player(&mut test);
+    wall(&mut test);
+    test.press_mouse(MouseButton::Left);
+    test.advance(30);
+    // the gun fires for one more frame after release
+    test.release_mouse(MouseButton::Left);
+    test.advance(2);
+
+    let points = test.point_count();
+    assert!(points > 0);
+    assert_eq!(points, test.shots_fired());
+    assert_eq!(points, test.point_entity_count());
+    let space = test.world().resource::<Space<VecStorage>>();
+    for (point, _) in &space.accelerator.points {
+        assert!((point.position.x - 9.5).abs() < 1e-3, "{point:?}");
+        assert!(
+            point.normal.x < -0.99,
+            "normal should face the gun, {point:?}"
+        );
+    }
+    assert!(test.world().resource::<MissLog>().0.is_empty());
+}
+
+#[test]
+fn shots_out_of_range_are_misses() {
+    let mut test = TestApp::new();
+    test.spawn_player(Transform::IDENTITY, LidarGun::new(0.05, 600.0, 5.0));
+    wall(&mut test);
+    test.press_mouse(MouseButton::Left);
+    test.advance(10);
+
+    assert_eq!(test.point_count(), 0);
+    assert_eq!(
+        test.world().resource::<MissLog>().0.len(),
+        test.shots_fired()
+    );
+    // misses are saved, so they count as unsaved scan data
+    assert!(test.world().resource::<UnsavedScan>().0);
+}
+
+#[test]
+fn point_limit_trims_oldest_points() {
+    let mut test = TestApp::new();
+    let mut gun = LidarGun::new(0.0, 600.0, 100.0);
+    gun.pattern = ScanPattern::Rangefinder;
+    test.spawn_player(Transform::IDENTITY, gun);
+    wall(&mut test);
+    test.world_mut()
+        .resource_mut::<Space<VecStorage>>()
+        .accelerator
+        .limit = 50;
+    test.press_mouse(MouseButton::Left);
+    test.advance(30);
+    test.release_mouse(MouseButton::Left);
+    test.advance(2);
+
+    assert!(test.shots_fired() > 50);
+    assert_eq!(test.point_count(), 50);
+    assert_eq!(test.point_entity_count(), 50);
+}
+
+/// a rangefinder at 10 shots a frame into the wall, resolving `per_frame` of them and keeping `pending` waiting
+fn capped_rangefinder(per_frame: u32, pending: usize) -> TestApp {
+    let mut test = TestApp::new();
+    let mut gun = LidarGun::new(0.0, 600.0, 100.0);
+    gun.pattern = ScanPattern::Rangefinder;
+    test.spawn_player(Transform::IDENTITY, gun);
+    wall(&mut test);
+    test.settings_mut().max_shots_per_frame = per_frame;
+    test.settings_mut().max_pending_shots = pending;
+    test
+}
+
+#[test]
+fn shots_over_the_cap_carry_over() {
+    let mut test = capped_rangefinder(4, 10_000);
+    test.press_mouse(MouseButton::Left);
+    test.advance(30);
+    test.release_mouse(MouseButton::Left);
+    test.advance(2);
+    assert!(test.point_count() < test.shots_fired());
+    assert!(!test.world().resource::<PendingShots>().0.is_empty());
+
+    // drained at 4 a frame once the gun stops
+    test.advance(100);
+    assert!(test.world().resource::<PendingShots>().0.is_empty());
+    assert_eq!(test.point_count(), test.shots_fired());
+}
+
+#[test]
+fn pending_shots_are_bounded() {
+    let mut test = capped_rangefinder(4, 20);
+    test.press_mouse(MouseButton::Left);
+    for _ in 0..30 {
+        test.advance(1);
+        assert!(test.world().resource::<PendingShots>().0.len() <= 20);
+    }
+    test.release_mouse(MouseButton::Left);
+    test.advance(100);
+
+    assert!(test.world().resource::<PendingShots>().0.is_empty());
+    assert!(test.point_count() < test.shots_fired());
+    assert!(test.point_count() >= 30 * 4);
+}
+
+#[test]
+fn walls_stop_the_player() {
+    let mut test = TestApp::new();
+    player(&mut test);
+    // long enough not to walk around it
+    test.spawn_target(
+        Cuboid::new(1.0, 20.0, 200.0),
+        Transform::from_xyz(10.0, 0.0, 0.0),
+    );
+    test.press(KeyCode::KeyW);
+    // walking diagonally into the wall, then sliding along it
+    test.press(KeyCode::KeyA);
+    test.advance_secs(2.0);
+
+    let translation = test.player_transform().translation;
+    assert!(
+        (translation.x - (9.5 - PLAYER_RADIUS)).abs() < 1e-3,
+        "moved to {translation}"
+    );
+    assert!(
+        translation.z.abs() > 5.0,
+        "should slide along the wall, {translation}"
+    );
+}
+
+#[test]
+fn grazing_approach_keeps_the_radius() {
+    let mut test = TestApp::new();
+    // heading almost straight along -z, drifting towards the wall at 0.05 m per meter
+    test.spawn_player(
+        Transform::from_xyz(9.0, 0.0, 0.0).with_rotation(Quat::from_rotation_y(FRAC_PI_2 - 0.05)),
+        LidarGun::new(0.05, 600.0, 100.0),
+    );
+    test.spawn_target(
+        Cuboid::new(1.0, 20.0, 200.0),
+        Transform::from_xyz(10.0, 0.0, 0.0),
+    );
+    test.press(KeyCode::KeyW);
+    test.advance_secs(3.0);
+
+    let translation = test.player_transform().translation;
+    assert!(
+        (translation.x - (9.5 - PLAYER_RADIUS)).abs() < 1e-3,
+        "moved to {translation}"
+    );
+    assert!(translation.z < -20.0, "should keep sliding, {translation}");
+}
+
+#[test]
+fn bvh_follows_moved_targets() {
+    let mut test = TestApp::new();
+    wall(&mut test);
+    test.advance(1);
+    let ray = Ray3d::new(Vec3::ZERO, Dir3::X);
+    let hit = test.world().resource::<SceneBvh>().cast_ray(ray, 100.0);
+    assert!((hit.unwrap().distance - 9.5).abs() < 1e-4);
+
+    let mut walls = test
+        .world_mut()
+        .query_filtered::<&mut Transform, With<LidarInteractable>>();
+    walls.single_mut(test.world_mut()).translation.x = 20.0;
+    // picked up once propagation has updated the GlobalTransform
+    test.advance(1);
+    let hit = test.world().resource::<SceneBvh>().cast_ray(ray, 100.0);
+    assert!((hit.unwrap().distance - 19.5).abs() < 1e-4);
+
+    let entity = test
+        .world_mut()
+        .query_filtered::<Entity, With<LidarInteractable>>()
+        .single(test.world());
+    test.world_mut().despawn(entity);
+    test.advance(1);
+    assert!(test.world().resource::<SceneBvh>().is_empty());
+}
+
+#[test]
+fn voxels_merge_repeated_hits() {
+    let mut test = capped_rangefinder(10_000, 10_000);
+    test.settings_mut().voxel_size = 0.5;
+    test.press_mouse(MouseButton::Left);
+    test.advance(3);
+    let first = test
+        .world()
+        .resource::<Space<VecStorage>>()
+        .accelerator
+        .points[0]
+        .0;
+    test.advance(28);
+
+    // every shot lands on the same spot
+    assert!(test.shots_fired() > 100);
+    assert_eq!(test.point_count(), 1);
+    assert_eq!(test.point_entity_count(), 1);
+    let point = test
+        .world()
+        .resource::<Space<VecStorage>>()
+        .accelerator
+        .points[0]
+        .0;
+    assert_eq!(point.position, first.position);
+    assert!(point.timestamp > first.timestamp);
+    let component = *test.world_mut().query::<&LidarPoint>().single(test.world());
+    assert_eq!(component.timestamp, point.timestamp);
+    assert_eq!(point.returns as usize, test.shots_fired());
+}
+
+#[test]
+fn loading_finishes_into_the_main_menu() {
+    let game = GameSettings {
+        gun_fire_rate: 123.0,
+        ..test_settings()
+    };
+    let mut test = TestApp::loading(Cli::default(), UserSettings::default(), game);
+    test.advance(2);
+
+    assert_eq!(test.state(), GameState::MainMenu);
+    assert_eq!(test.world().resource::<GameSettings>().gun_fire_rate, 123.0);
+    assert!(test.world().contains_resource::<SettingsLoaded>());
+}
+
+#[test]
+fn quickstart_skips_the_main_menu() {
+    let cli = Cli {
+        quickstart: true,
+        ..default()
+    };
+    let mut test = TestApp::loading(cli, UserSettings::default(), test_settings());
+    test.advance(2);
+
+    assert_eq!(test.state(), GameState::InGame);
+}
+
+#[test]
+fn loading_waits_for_tracked_assets() {
+    let mut test = TestApp::loading(Cli::default(), UserSettings::default(), test_settings());
+    // a handle the asset server never hears about stays not loaded
+    let pending = test
+        .world()
+        .resource::<Assets<GameSettings>>()
+        .reserve_handle();
+    test.world_mut()
+        .resource_mut::<AssetsTracking>()
+        .add(pending.untyped());
+    test.advance(5);
+
+    assert_eq!(test.state(), GameState::Loading);
+    // quitting now must not write the default user settings over the player's file
+    assert!(!test.world().contains_resource::<SettingsLoaded>());
+}